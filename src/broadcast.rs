@@ -0,0 +1,104 @@
+use {IndexSlice, IndexNd, ViewDesc};
+
+use std::error::{Error};
+use std::fmt;
+
+/// Two shapes disagree along an axis where neither size is 1.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BroadcastError {
+  pub axis: usize,
+  pub lhs: usize,
+  pub rhs: usize,
+}
+
+impl fmt::Display for BroadcastError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "cannot broadcast sizes {} and {} along axis {}", self.lhs, self.rhs, self.axis)
+  }
+}
+
+impl Error for BroadcastError {
+}
+
+fn broadcast_size(axis: usize, lhs: usize, rhs: usize) -> Result<usize, BroadcastError> {
+  if lhs == rhs || rhs == 1 {
+    Ok(lhs)
+  } else if lhs == 1 {
+    Ok(rhs)
+  } else {
+    Err(BroadcastError{axis, lhs, rhs})
+  }
+}
+
+/// Broadcast two shapes of the same type: along each axis the sizes must be
+/// equal, or one of them must be 1. Dynamic-rank shapes of different ranks
+/// are matched as in `broadcast_shapes`.
+pub fn broadcast_shape<I: IndexSlice>(lhs: &I, rhs: &I) -> Result<I, BroadcastError> {
+  let mut shape = if lhs.as_slice().len() >= rhs.as_slice().len() {
+    lhs.clone()
+  } else {
+    rhs.clone()
+  };
+  let lhs = lhs.as_slice();
+  let rhs = rhs.as_slice();
+  for (d, n) in shape.as_mut_slice().iter_mut().enumerate() {
+    let l = lhs.get(d).cloned().unwrap_or(1);
+    let r = rhs.get(d).cloned().unwrap_or(1);
+    *n = broadcast_size(d, l, r)?;
+  }
+  Ok(shape)
+}
+
+/// Broadcast two shapes of possibly different ranks. Axes are matched from
+/// axis 0 (the inside), and the missing outer axes of the lower-rank shape
+/// count as size 1; this is NumPy's trailing-axis rule in the crate's
+/// column-major axis order.
+pub fn broadcast_shapes<A: IndexSlice, B: IndexSlice>(lhs: &A, rhs: &B) -> Result<IndexNd, BroadcastError> {
+  let lhs = lhs.as_slice();
+  let rhs = rhs.as_slice();
+  let dim = lhs.len().max(rhs.len());
+  let mut shape = IndexNd::zero(dim);
+  for d in 0 .. dim {
+    let l = lhs.get(d).cloned().unwrap_or(1);
+    let r = rhs.get(d).cloned().unwrap_or(1);
+    shape.components[d] = broadcast_size(d, l, r)?;
+  }
+  Ok(shape)
+}
+
+/// The stride with which to read an input of the given shape and stride at
+/// the indices of `out_shape`: broadcast axes get stride 0, so that
+/// `flat_index` of an output index addresses the right input element. Axes
+/// are matched as in `broadcast_shapes`.
+pub fn broadcast_stride<I: IndexSlice, O: IndexSlice>(shape: &I, stride: &I, out_shape: &O) -> Result<O, BroadcastError> {
+  let in_shape = shape.as_slice();
+  let in_stride = stride.as_slice();
+  let out_dim = out_shape.as_slice().len();
+  for (d, &n) in in_shape.iter().enumerate().skip(out_dim) {
+    if n != 1 {
+      return Err(BroadcastError{axis: d, lhs: n, rhs: 1});
+    }
+  }
+  let mut out_stride = out_shape.clone();
+  for (d, (s, &m)) in out_stride.as_mut_slice().iter_mut().zip(out_shape.as_slice()).enumerate() {
+    let n = in_shape.get(d).cloned().unwrap_or(1);
+    *s = if n == m {
+      // A missing input axis has size 1, and so any stride; use 0.
+      in_stride.get(d).cloned().unwrap_or(0)
+    } else if n == 1 {
+      0
+    } else {
+      return Err(BroadcastError{axis: d, lhs: n, rhs: m});
+    };
+  }
+  Ok(out_stride)
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  /// View `self` as having shape `out_shape`, with zero strides along the
+  /// broadcast axes.
+  pub fn broadcast_to<O: IndexSlice>(&self, out_shape: &O) -> Result<ViewDesc<O>, BroadcastError> {
+    let stride = broadcast_stride(&self.shape, &self.stride, out_shape)?;
+    Ok(ViewDesc{shape: out_shape.clone(), stride, offset: self.offset})
+  }
+}
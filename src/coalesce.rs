@@ -0,0 +1,117 @@
+use {IndexSlice, IndexNd, Permutation, ViewDesc};
+
+use std::cmp::{Ordering};
+
+/// Merge adjacent axes that every operand traverses contiguously, i.e.
+/// axes `d` and `d + 1` where `stride[d + 1] == stride[d] * shape[d]` for each
+/// of `strides`, and drop size-1 axes. The returned lower-rank shape and
+/// strides visit the same offsets, in the same order, as the originals. An
+/// empty shape coalesces to a single axis of size 0.
+pub fn coalesce<I: IndexSlice>(shape: &I, strides: &[&I]) -> (IndexNd, Vec<IndexNd>) {
+  let shape = shape.as_slice();
+  for stride in strides.iter() {
+    assert_eq!(shape.len(), stride.as_slice().len());
+  }
+  let mut out_shape = IndexNd::default();
+  let mut out_strides = vec![IndexNd::default(); strides.len()];
+  if shape.contains(&0) {
+    out_shape.components.push(0);
+    for out_stride in out_strides.iter_mut() {
+      out_stride.components.push(0);
+    }
+    return (out_shape, out_strides);
+  }
+  for (d, &n) in shape.iter().enumerate() {
+    if n == 1 {
+      continue;
+    }
+    if let Some(&m) = out_shape.components.last() {
+      let contiguous = strides.iter().zip(out_strides.iter()).all(|(stride, out_stride)| {
+        let s = out_stride.components[out_stride.dim() - 1];
+        stride.as_slice()[d] == s * m
+      });
+      if contiguous {
+        *out_shape.components.last_mut().unwrap() = m * n;
+        continue;
+      }
+    }
+    out_shape.components.push(n);
+    for (stride, out_stride) in strides.iter().zip(out_strides.iter_mut()) {
+      out_stride.components.push(stride.as_slice()[d]);
+    }
+  }
+  (out_shape, out_strides)
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  /// The same view with its axes coalesced; see `coalesce`.
+  pub fn coalesce(&self) -> ViewDesc<IndexNd> {
+    let (shape, mut strides) = coalesce(&self.shape, &[&self.stride]);
+    ViewDesc{shape, stride: strides.pop().unwrap(), offset: self.offset}
+  }
+}
+
+/// A canonical loop nest for iterating several operands over a common
+/// shape: axes are reordered so that axis 0 (the innermost loop) is as
+/// contiguous as possible across all operands, then size-1 axes are dropped
+/// and the rest coalesced.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct IterPlan {
+  pub shape: IndexNd,
+  pub strides: Vec<IndexNd>,
+}
+
+impl IterPlan {
+  pub fn new<I: IndexSlice>(shape: &I, strides: &[&I]) -> Self {
+    let perm = contiguous_order(shape, strides);
+    let shape = shape.permute_axes(&perm);
+    let strides: Vec<I> = strides.iter().map(|s| s.permute_axes(&perm)).collect();
+    let stride_refs: Vec<&I> = strides.iter().collect();
+    let (shape, strides) = coalesce(&shape, &stride_refs);
+    IterPlan{shape, strides}
+  }
+
+  pub fn dim(&self) -> usize {
+    self.shape.dim()
+  }
+}
+
+/// Choose an axis order with smaller strides inside, by insertion sort. Axis
+/// `a` belongs outside axis `b` if the first operand with nonzero strides
+/// along both axes has a larger stride along `a` (ties go to the larger
+/// size). Axes no operand can order keep their relative order.
+fn contiguous_order<I: IndexSlice>(shape: &I, strides: &[&I]) -> Permutation {
+  let shape = shape.as_slice();
+  let compare = |a: usize, b: usize| -> Ordering {
+    for stride in strides.iter() {
+      let (sa, sb) = (stride.as_slice()[a], stride.as_slice()[b]);
+      if sa == 0 || sb == 0 {
+        continue;
+      }
+      match sa.cmp(&sb) {
+        Ordering::Equal => {}
+        ord => return ord,
+      }
+      match shape[a].cmp(&shape[b]) {
+        Ordering::Equal => {}
+        ord => return ord,
+      }
+    }
+    Ordering::Equal
+  };
+  let mut axes: Vec<usize> = (0 .. shape.len()).collect();
+  for i in 1 .. axes.len() {
+    let mut inner = i;
+    for outer in (0 .. i).rev() {
+      match compare(axes[outer], axes[inner]) {
+        Ordering::Greater => {
+          axes.swap(outer, inner);
+          inner = outer;
+        }
+        Ordering::Less => break,
+        Ordering::Equal => {}
+      }
+    }
+  }
+  Permutation::new(axes).unwrap()
+}
@@ -0,0 +1,287 @@
+use {IndexSlice, IndexNd, ViewDesc, coalesce, unflat_into};
+
+use std::iter::{FusedIterator};
+
+/// Iterator over every index inside a shape, in the crate's inside-first
+/// order: axis 0 varies fastest, the outermost axis varies slowest.
+#[derive(Clone, Debug)]
+pub struct MultiIndexIter<I> {
+  shape: I,
+  head: I,
+  tail: I,
+  front: usize,
+  back: usize,
+}
+
+impl<I: IndexSlice> MultiIndexIter<I> {
+  /// Panics if the flat length of `shape` overflows a `usize`, since the
+  /// iterator could not report its exact length.
+  pub fn new(shape: I) -> Self {
+    let len = match shape.checked_flat_len() {
+      Some(len) => len,
+      None if shape.as_slice().contains(&0) => 0,
+      None => panic!("flat length of shape {:?} overflows a usize", shape.as_slice()),
+    };
+    let mut head = shape.clone();
+    let mut tail = shape.clone();
+    unflat_into(shape.as_slice(), 0, head.as_mut_slice());
+    unflat_into(shape.as_slice(), len.saturating_sub(1), tail.as_mut_slice());
+    MultiIndexIter{shape, head, tail, front: 0, back: len}
+  }
+
+  pub fn shape(&self) -> &I {
+    &self.shape
+  }
+}
+
+impl<I: IndexSlice> Iterator for MultiIndexIter<I> {
+  type Item = I;
+
+  fn next(&mut self) -> Option<I> {
+    if self.front >= self.back {
+      return None;
+    }
+    let idx = self.head.clone();
+    self.front += 1;
+    if self.front < self.back {
+      step_forward(self.shape.as_slice(), self.head.as_mut_slice());
+    }
+    Some(idx)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = self.back - self.front;
+    (len, Some(len))
+  }
+
+  fn nth(&mut self, n: usize) -> Option<I> {
+    if n >= self.back - self.front {
+      self.front = self.back;
+      return None;
+    }
+    self.front += n;
+    unflat_into(self.shape.as_slice(), self.front, self.head.as_mut_slice());
+    self.next()
+  }
+}
+
+impl<I: IndexSlice> DoubleEndedIterator for MultiIndexIter<I> {
+  fn next_back(&mut self) -> Option<I> {
+    if self.front >= self.back {
+      return None;
+    }
+    let idx = self.tail.clone();
+    self.back -= 1;
+    if self.front < self.back {
+      step_backward(self.shape.as_slice(), self.tail.as_mut_slice());
+    }
+    Some(idx)
+  }
+
+  fn nth_back(&mut self, n: usize) -> Option<I> {
+    if n >= self.back - self.front {
+      self.back = self.front;
+      return None;
+    }
+    self.back -= n;
+    unflat_into(self.shape.as_slice(), self.back - 1, self.tail.as_mut_slice());
+    self.next_back()
+  }
+}
+
+impl<I: IndexSlice> ExactSizeIterator for MultiIndexIter<I> {
+}
+
+impl<I: IndexSlice> FusedIterator for MultiIndexIter<I> {
+}
+
+/// Iterator over the maximal contiguous runs of a strided region, yielding
+/// `(flat_offset, run_len)` pairs in the region's inside-first order. Packed
+/// inner axes are coalesced into a single run; every run has the same length.
+#[derive(Clone, Debug)]
+pub struct RunIter {
+  outer: MultiIndexIter<IndexNd>,
+  outer_stride: IndexNd,
+  offset: usize,
+  run_len: usize,
+}
+
+impl RunIter {
+  pub fn new<I: IndexSlice>(shape: &I, stride: &I, offset: usize) -> Self {
+    let (mut shape, mut strides) = coalesce(shape, &[stride]);
+    let mut stride = strides.pop().unwrap();
+    let mut run_len = 1;
+    if shape.dim() > 0 && stride[0] == 1 {
+      run_len = shape.components.remove(0);
+      stride.components.remove(0);
+    }
+    RunIter{
+      outer: MultiIndexIter::new(shape),
+      outer_stride: stride,
+      offset,
+      run_len,
+    }
+  }
+
+  /// The length of every run.
+  pub fn run_len(&self) -> usize {
+    self.run_len
+  }
+
+  /// The number of runs remaining.
+  pub fn num_runs(&self) -> usize {
+    self.outer.len()
+  }
+
+  fn run_at(&self, idx: &IndexNd) -> (usize, usize) {
+    let disp: usize = idx.components.iter().zip(self.outer_stride.components.iter())
+      .map(|(&i, &s)| i * s)
+      .sum();
+    (self.offset + disp, self.run_len)
+  }
+}
+
+impl Iterator for RunIter {
+  type Item = (usize, usize);
+
+  fn next(&mut self) -> Option<(usize, usize)> {
+    self.outer.next().map(|idx| self.run_at(&idx))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.outer.size_hint()
+  }
+
+  fn nth(&mut self, n: usize) -> Option<(usize, usize)> {
+    self.outer.nth(n).map(|idx| self.run_at(&idx))
+  }
+}
+
+impl DoubleEndedIterator for RunIter {
+  fn next_back(&mut self) -> Option<(usize, usize)> {
+    self.outer.next_back().map(|idx| self.run_at(&idx))
+  }
+}
+
+impl ExactSizeIterator for RunIter {
+}
+
+impl FusedIterator for RunIter {
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  /// The contiguous runs of the view; see `RunIter`.
+  pub fn runs(&self) -> RunIter {
+    RunIter::new(&self.shape, &self.stride, self.offset)
+  }
+}
+
+/// Iterator over every index inside a shape, in the same order as
+/// `MultiIndexIter`, paired with its flat offset under each of `K` strided
+/// layouts. The offsets are updated by stride deltas as the index advances,
+/// rather than recomputed with `flat_index` for every element.
+#[derive(Clone, Debug)]
+pub struct OffsetIter<I, const K: usize> {
+  shape: I,
+  strides: [I; K],
+  backstrides: [I; K],
+  idx: I,
+  offsets: [usize; K],
+  remaining: usize,
+}
+
+impl<I: IndexSlice, const K: usize> OffsetIter<I, K> {
+  /// Iterate `shape` with the `k`-th offset starting at `base[k]` and
+  /// advancing by `strides[k]`.
+  pub fn new(shape: I, strides: [I; K], base: [usize; K]) -> Self {
+    let remaining = shape.as_slice().iter().product();
+    let mut idx = shape.clone();
+    for i in idx.as_mut_slice().iter_mut() {
+      *i = 0;
+    }
+    // The displacement along each axis from its first to its last index,
+    // undone when that axis wraps around.
+    let backstrides = strides.clone().map(|stride| {
+      let mut back = stride.clone();
+      for (b, &n) in back.as_mut_slice().iter_mut().zip(shape.as_slice()) {
+        *b *= n.saturating_sub(1);
+      }
+      back
+    });
+    OffsetIter{shape, strides, backstrides, idx, offsets: base, remaining}
+  }
+
+  pub fn shape(&self) -> &I {
+    &self.shape
+  }
+
+  fn advance(&mut self) {
+    let shape = self.shape.as_slice();
+    for (d, i) in self.idx.as_mut_slice().iter_mut().enumerate() {
+      if *i + 1 < shape[d] {
+        *i += 1;
+        for (off, stride) in self.offsets.iter_mut().zip(self.strides.iter()) {
+          *off += stride.as_slice()[d];
+        }
+        return;
+      }
+      *i = 0;
+      for (off, back) in self.offsets.iter_mut().zip(self.backstrides.iter()) {
+        *off -= back.as_slice()[d];
+      }
+    }
+  }
+}
+
+impl<I: IndexSlice, const K: usize> Iterator for OffsetIter<I, K> {
+  type Item = (I, [usize; K]);
+
+  fn next(&mut self) -> Option<(I, [usize; K])> {
+    if self.remaining == 0 {
+      return None;
+    }
+    let item = (self.idx.clone(), self.offsets);
+    self.remaining -= 1;
+    if self.remaining > 0 {
+      self.advance();
+    }
+    Some(item)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<I: IndexSlice, const K: usize> ExactSizeIterator for OffsetIter<I, K> {
+}
+
+impl<I: IndexSlice, const K: usize> FusedIterator for OffsetIter<I, K> {
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  /// Every index of the view paired with its flat offset; see `OffsetIter`.
+  pub fn offsets(&self) -> OffsetIter<I, 1> {
+    OffsetIter::new(self.shape.clone(), [self.stride.clone()], [self.offset])
+  }
+}
+
+fn step_forward(shape: &[usize], idx: &mut [usize]) {
+  for (i, &s) in idx.iter_mut().zip(shape) {
+    *i += 1;
+    if *i < s {
+      return;
+    }
+    *i = 0;
+  }
+}
+
+fn step_backward(shape: &[usize], idx: &mut [usize]) {
+  for (i, &s) in idx.iter_mut().zip(shape) {
+    if *i > 0 {
+      *i -= 1;
+      return;
+    }
+    *i = s - 1;
+  }
+}
@@ -1,14 +1,108 @@
 use std::collections::{Bound};
-use std::fmt::{Debug};
+use std::convert::{TryFrom};
+use std::error::{Error};
+use std::fmt::{self, Debug};
 use std::hash::{Hash};
+use std::slice;
 use std::ops::{Index, RangeBounds};
 
+pub use broadcast::{BroadcastError, broadcast_shape, broadcast_shapes, broadcast_stride};
+pub use coalesce::{IterPlan, coalesce};
+pub use iter::{MultiIndexIter, OffsetIter, RunIter};
+pub use permute::{Permutation};
+pub use reshape::{infer_shape, try_reshape, try_reshape_infer};
+pub use small::{SMALL_INDEX_CAP, SmallIndexNd, SmallSIndexNd};
+pub use unravel::{FastDivisor, Unraveler};
+pub use view::{ViewDesc, SViewDesc};
+
+pub mod broadcast;
+pub mod coalesce;
+pub mod iter;
+pub mod permute;
+pub mod reshape;
+pub mod small;
+pub mod unravel;
+pub mod view;
+
 // TODO: figure out axis API.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Ax(pub usize);
 
+/// Order in which the axes of a packed array are laid out in memory.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub enum Layout {
+  /// Axis 0 varies fastest (Fortran order). This is the crate's default, and
+  /// the convention of `to_packed_stride`, `inside` and `outside`.
+  #[default]
+  ColumnMajor,
+  /// The last axis varies fastest (C/NumPy order).
+  RowMajor,
+}
+
+/// Errors from the fallible (`try_`) versions of the index APIs.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct UnimplIndex;
+pub enum ArrayIdxError {
+  /// An index or shape has the wrong number of axes.
+  RankMismatch{expected: usize, found: usize},
+  /// An axis is not one of the axes of an index of rank `ndim`.
+  AxisOutOfRange{axis: isize, ndim: usize},
+  /// An index is past the end of an axis of length `size`.
+  OutOfBounds{index: usize, size: usize},
+  /// Index arithmetic overflowed a `usize`.
+  Overflow,
+  /// A range starts after it ends.
+  InvertedRange{start: usize, end: usize},
+  /// Two shapes cannot be broadcast together.
+  Broadcast(BroadcastError),
+  /// An inline index of at most `capacity` axes was given `found` axes.
+  CapacityExceeded{capacity: usize, found: usize},
+}
+
+impl fmt::Display for ArrayIdxError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ArrayIdxError::RankMismatch{expected, found} => {
+        write!(f, "rank mismatch: expected {} axes, found {}", expected, found)
+      }
+      ArrayIdxError::AxisOutOfRange{axis, ndim} => {
+        write!(f, "axis {} out of range for rank {}", axis, ndim)
+      }
+      ArrayIdxError::OutOfBounds{index, size} => {
+        write!(f, "array end bounds violation: {} greater than {}", index, size)
+      }
+      ArrayIdxError::Overflow => {
+        write!(f, "index arithmetic overflow")
+      }
+      ArrayIdxError::InvertedRange{start, end} => {
+        write!(f, "array bounds violation: {} greater than {}", start, end)
+      }
+      ArrayIdxError::Broadcast(ref e) => {
+        fmt::Display::fmt(e, f)
+      }
+      ArrayIdxError::CapacityExceeded{capacity, found} => {
+        write!(f, "rank {} exceeds the inline capacity {}", found, capacity)
+      }
+    }
+  }
+}
+
+impl Error for ArrayIdxError {
+}
+
+impl From<BroadcastError> for ArrayIdxError {
+  fn from(e: BroadcastError) -> Self {
+    ArrayIdxError::Broadcast(e)
+  }
+}
+
+impl Layout {
+  pub fn transpose(self) -> Layout {
+    match self {
+      Layout::ColumnMajor => Layout::RowMajor,
+      Layout::RowMajor => Layout::ColumnMajor,
+    }
+  }
+}
 
 pub type Index0d = ();
 pub type Index1d = usize;
@@ -16,16 +110,25 @@ pub type Index2d = [usize; 2];
 pub type Index3d = [usize; 3];
 pub type Index4d = [usize; 4];
 pub type Index5d = [usize; 5];
-
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub type Index6d = [usize; 6];
+pub type Index7d = [usize; 7];
+pub type Index8d = [usize; 8];
+
+/// Signed counterparts of the index types, used for strides that may step
+/// backwards through a buffer.
+pub type SIndex0d = ();
+pub type SIndex1d = isize;
+pub type SIndex2d = [isize; 2];
+pub type SIndex3d = [isize; 3];
+pub type SIndex4d = [isize; 4];
+pub type SIndex5d = [isize; 5];
+pub type SIndex6d = [isize; 6];
+pub type SIndex7d = [isize; 7];
+pub type SIndex8d = [isize; 8];
+
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug)]
 pub struct IndexNd{pub components: Vec<usize>}
 
-impl Default for IndexNd {
-  fn default() -> Self {
-    IndexNd{components: vec![]}
-  }
-}
-
 impl Index<usize> for IndexNd {
   type Output = usize;
 
@@ -34,17 +137,15 @@ impl Index<usize> for IndexNd {
   }
 }
 
-impl IndexNd {
-  pub fn from(components: Vec<usize>) -> Self {
+impl From<Vec<usize>> for IndexNd {
+  fn from(components: Vec<usize>) -> Self {
     IndexNd{components}
   }
+}
 
+impl IndexNd {
   pub fn zero(dim: usize) -> Self {
-    let mut components = Vec::with_capacity(dim);
-    for _ in 0 .. dim {
-      components.push(0);
-    }
-    IndexNd{components}
+    IndexNd{components: vec![0; dim]}
   }
 
   pub fn flat_len(&self) -> usize {
@@ -56,7 +157,7 @@ impl IndexNd {
   }
 
   pub fn index_at(&self, axis: isize) -> usize {
-    self.components[axis as usize]
+    self.components[unwrap_axis(axis, self.dim())]
   }
 
   pub fn to_packed_stride(&self) -> Self {
@@ -84,11 +185,11 @@ impl IndexNd {
   }
 
   pub fn inside(&self) -> usize {
-    self.components[0]
+    self.inside_in(Layout::ColumnMajor)
   }
 
   pub fn outside(&self) -> usize {
-    self.components[self.dim() - 1]
+    self.outside_in(Layout::ColumnMajor)
   }
 
   pub fn dim(&self) -> usize {
@@ -99,481 +200,774 @@ impl IndexNd {
     self.dim()
   }
 
+  /// Splits the index around `axis`. Negative axes count back from the last
+  /// axis; `axis == dim()` splits past the end, leaving only the prefix.
   pub fn splice_at(&self, axis: isize) -> (IndexNd, IndexNd, IndexNd) {
+    let axis = if axis == self.dim() as isize {
+      self.dim()
+    } else {
+      unwrap_axis(axis, self.dim())
+    };
     let mut prefix_idx = IndexNd::default();
     for prefix_axis in 0 .. axis {
-      prefix_idx.components.push(self.index_at(prefix_axis));
+      prefix_idx.components.push(self.components[prefix_axis]);
     }
     let mut select_idx = IndexNd::default();
-    if axis < self.dim() as isize {
-      select_idx.components.push(self.index_at(axis));
+    if axis < self.dim() {
+      select_idx.components.push(self.components[axis]);
     }
     let mut suffix_idx = IndexNd::default();
-    for suffix_axis in axis + 1 .. self.dim() as isize {
-      suffix_idx.components.push(self.index_at(suffix_axis));
+    for suffix_axis in axis + 1 .. self.dim() {
+      suffix_idx.components.push(self.components[suffix_axis]);
     }
     (prefix_idx, select_idx, suffix_idx)
   }
 }
 
-pub trait ArrayIndex: Clone + PartialEq + Eq + Hash + Debug {
-  type Above: ArrayIndex + Sized;
-  type Below: ArrayIndex + Sized;
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug)]
+pub struct SIndexNd{pub components: Vec<isize>}
 
-  fn zero() -> Self where Self: Sized;
+impl Index<usize> for SIndexNd {
+  type Output = isize;
 
-  fn from_nd(nd_shape: Vec<usize>) -> Self where Self: Sized;
-  fn to_nd(&self) -> Vec<usize>;
-  fn _to_nd(&self) -> IndexNd {
-    IndexNd{components: self.to_nd()}
+  fn index(&self, index: usize) -> &isize {
+    &self.components[index]
   }
+}
 
-  fn index_add(&self, shift: &Self) -> Self where Self: Sized;
-  fn index_sub(&self, shift: &Self) -> Self where Self: Sized;
+impl From<Vec<isize>> for SIndexNd {
+  fn from(components: Vec<isize>) -> Self {
+    SIndexNd{components}
+  }
+}
 
-  fn index_prepend(&self, new_inside: usize) -> Self::Above;
-  fn index_append(&self, new_outside: usize) -> Self::Above;
+impl SIndexNd {
+  pub fn dim(&self) -> usize {
+    self.components.len()
+  }
+}
 
-  fn index_at(&self, axis: isize) -> usize;
-  fn index_cut(&self, axis: isize) -> Self::Below;
+/// Uniform access to the components of a signed index; the signed
+/// counterpart of `IndexSlice`.
+pub trait SIndexSlice: Clone + PartialEq + Eq + Hash + Debug {
+  fn as_signed_slice(&self) -> &[isize];
+  fn as_mut_signed_slice(&mut self) -> &mut [isize];
 
-  fn to_packed_stride(&self) -> Self where Self: Sized;
-  fn is_packed(&self, stride: &Self) -> bool where Self: Sized;
-  fn stride_append_packed(&self, outside: usize) -> Self::Above where Self: Sized {
-    self.index_append(self.outside() * outside)
+  fn is_nonnegative(&self) -> bool {
+    self.as_signed_slice().iter().all(|&s| s >= 0)
   }
+}
 
-  fn flat_len(&self) -> usize;
-  fn flat_index(&self, stride: &Self) -> usize;
-
-  fn inside(&self) -> usize;
-  fn outside(&self) -> usize;
+impl SIndexSlice for SIndex0d {
+  fn as_signed_slice(&self) -> &[isize] {
+    &[]
+  }
 
-  fn dim(&self) -> usize;
-  fn ndim(&self) -> usize {
-    self.dim()
+  fn as_mut_signed_slice(&mut self) -> &mut [isize] {
+    &mut []
   }
 }
 
-impl ArrayIndex for Index0d {
-  type Above = Index1d;
-  type Below = Index0d;
+impl SIndexSlice for SIndex1d {
+  fn as_signed_slice(&self) -> &[isize] {
+    slice::from_ref(self)
+  }
 
-  fn zero() -> Self {
-    ()
+  fn as_mut_signed_slice(&mut self) -> &mut [isize] {
+    slice::from_mut(self)
   }
+}
 
-  fn from_nd(nd_shape: Vec<usize>) -> Self {
-    assert_eq!(0, nd_shape.len());
-    ()
+impl SIndexSlice for SIndexNd {
+  fn as_signed_slice(&self) -> &[isize] {
+    &self.components
   }
 
-  fn to_nd(&self) -> Vec<usize> {
-    vec![]
+  fn as_mut_signed_slice(&mut self) -> &mut [isize] {
+    &mut self.components
   }
+}
 
-  fn index_add(&self, shift: &Self) -> Self {
-    ()
+/// Uniform access to the components of an index, shared by the fixed-rank
+/// indices and `IndexNd`, so that rank-generic algorithms can be written once.
+pub trait IndexSlice: Clone {
+  /// The signed index type of the same rank.
+  type Signed: SIndexSlice;
+
+  fn as_slice(&self) -> &[usize];
+  fn as_mut_slice(&mut self) -> &mut [usize];
+
+  /// Panics if a component exceeds `isize::MAX`.
+  fn to_signed(&self) -> Self::Signed;
+
+  /// Fallible `to_signed`.
+  fn try_to_signed(&self) -> Result<Self::Signed, ArrayIdxError> {
+    if self.as_slice().iter().any(|&c| isize::try_from(c).is_err()) {
+      return Err(ArrayIdxError::Overflow);
+    }
+    Ok(self.to_signed())
   }
 
-  fn index_sub(&self, shift: &Self) -> Self {
-    ()
+  /// Displacement of the element at index `self` relative to the base offset
+  /// of a view with the given signed stride.
+  fn flat_index_signed(&self, stride: &Self::Signed) -> isize {
+    assert_eq!(self.as_slice().len(), stride.as_signed_slice().len());
+    self.as_slice().iter().zip(stride.as_signed_slice())
+      .map(|(&i, &s)| i as isize * s)
+      .sum()
   }
 
-  fn to_packed_stride(&self) -> Self {
-    ()
+  /// Iterate over every index inside the shape `self`, in the same
+  /// inside-first (axis 0 fastest) order as `to_packed_stride`. Panics if
+  /// the flat length of the shape overflows a `usize`.
+  fn indices(&self) -> MultiIndexIter<Self> where Self: Sized {
+    MultiIndexIter::new(self.clone())
   }
 
-  fn is_packed(&self, stride: &Self) -> bool {
-    true
+  /// Inverse of `flat_index` with the packed stride of `shape`: convert a
+  /// flat offset into an index, using the same column-major (axis 0 fastest)
+  /// convention as `to_packed_stride`. Panics if `offset` is not inside
+  /// `shape`.
+  fn unflat_index(shape: &Self, offset: usize) -> Self where Self: Sized {
+    match Self::try_unflat_index(shape, offset) {
+      Some(idx) => idx,
+      None => panic!("flat offset out of bounds: {} for shape {:?}", offset, shape.as_slice()),
+    }
   }
 
-  fn index_prepend(&self, major: usize) -> Index1d {
-    major
+  /// Like `unflat_index`, but returns `None` if `offset` is not less than the
+  /// flat length of `shape`.
+  fn try_unflat_index(shape: &Self, offset: usize) -> Option<Self> where Self: Sized {
+    let len: usize = shape.as_slice().iter().product();
+    if offset >= len {
+      return None;
+    }
+    let mut idx = shape.clone();
+    unflat_into(shape.as_slice(), offset, idx.as_mut_slice());
+    Some(idx)
+  }
+
+  fn to_packed_stride_in(&self, layout: Layout) -> Self where Self: Sized {
+    let mut stride = self.clone();
+    {
+      let pairs = stride.as_mut_slice().iter_mut().zip(self.as_slice());
+      let mut acc = 1;
+      let mut pack = |(s, &n): (&mut usize, &usize)| {
+        *s = acc;
+        acc *= n;
+      };
+      match layout {
+        Layout::ColumnMajor => pairs.for_each(&mut pack),
+        Layout::RowMajor => pairs.rev().for_each(&mut pack),
+      }
+    }
+    stride
   }
 
-  fn index_append(&self, minor: usize) -> Index1d {
-    minor
+  fn is_packed_in(&self, stride: &Self, layout: Layout) -> bool where Self: Sized {
+    self.to_packed_stride_in(layout).as_slice() == stride.as_slice()
   }
 
-  fn index_at(&self, _axis: isize) -> usize {
-    unreachable!();
+  fn to_packed_stride_c(&self) -> Self where Self: Sized {
+    self.to_packed_stride_in(Layout::RowMajor)
   }
 
-  fn index_cut(&self, _axis: isize) -> Index0d {
-    // TODO: any special handling for this case?
-    ()
+  fn is_packed_c(&self, stride: &Self) -> bool where Self: Sized {
+    self.is_packed_in(stride, Layout::RowMajor)
   }
 
-  fn flat_len(&self) -> usize {
-    1
+  /// The fastest-varying component under `layout`, or 1 for a 0-d index.
+  fn inside_in(&self, layout: Layout) -> usize {
+    let c = match layout {
+      Layout::ColumnMajor => self.as_slice().first(),
+      Layout::RowMajor => self.as_slice().last(),
+    };
+    c.cloned().unwrap_or(1)
   }
 
-  fn flat_index(&self, stride: &Self) -> usize {
-    0
+  /// The slowest-varying component under `layout`, or 1 for a 0-d index.
+  fn outside_in(&self, layout: Layout) -> usize {
+    self.inside_in(layout.transpose())
   }
 
-  fn inside(&self) -> usize {
-    1
+  fn inside_c(&self) -> usize {
+    self.inside_in(Layout::RowMajor)
   }
 
-  fn outside(&self) -> usize {
-    1
+  fn outside_c(&self) -> usize {
+    self.outside_in(Layout::RowMajor)
   }
 
-  fn dim(&self) -> usize {
-    0
+  /// Reverse the order of the axes. A buffer described by a shape and stride
+  /// in one `Layout` is described by the reversed shape and stride in the
+  /// other, e.g. `shape.to_packed_stride_c().reversed_axes()` equals
+  /// `shape.reversed_axes().to_packed_stride_in(Layout::ColumnMajor)`.
+  fn reversed_axes(&self) -> Self where Self: Sized {
+    let mut idx = self.clone();
+    idx.as_mut_slice().reverse();
+    idx
   }
-}
 
-impl ArrayIndex for Index1d {
-  type Above = Index2d;
-  type Below = Index0d;
+  /// Reorder the axes: axis `i` of the result is axis `perm[i]` of `self`.
+  fn permute_axes(&self, perm: &Permutation) -> Self where Self: Sized {
+    let mut idx = self.clone();
+    permute::permute_into(self.as_slice(), perm, idx.as_mut_slice());
+    idx
+  }
 
-  fn zero() -> Self {
-    0
+  /// The smallest offset reached by any index inside the shape `self` with
+  /// the given stride and base offset, or `None` if the shape is empty.
+  ///
+  /// Panics if the ranks of `self` and `stride` differ.
+  fn min_offset(&self, stride: &Self, offset: usize) -> Option<usize> {
+    if let Err(e) = check_span_rank(self.as_slice(), stride.as_slice().len()) {
+      panic!("{}", e);
+    }
+    if self.as_slice().contains(&0) {
+      return None;
+    }
+    Some(offset)
+  }
+
+  /// The largest offset reached by any index inside the shape `self` with
+  /// the given stride and base offset, or `None` if the shape is empty.
+  ///
+  /// Panics if the ranks of `self` and `stride` differ or the offset
+  /// overflows a `usize`.
+  fn max_offset(&self, stride: &Self, offset: usize) -> Option<usize> {
+    match span_max(self.as_slice(), stride.as_slice(), offset) {
+      Ok(max) => max,
+      Err(e) => panic!("{}", e),
+    }
   }
 
-  fn from_nd(nd_shape: Vec<usize>) -> Self {
-    assert_eq!(1, nd_shape.len());
-    nd_shape[0]
+  /// The minimum length of a buffer containing every offset reached by the
+  /// shape `self` with the given stride and base offset (0 if it is empty).
+  /// Panics in the same cases as `max_offset`.
+  fn required_len(&self, stride: &Self, offset: usize) -> usize {
+    match span_len(self.as_slice(), stride.as_slice(), offset) {
+      Ok(len) => len,
+      Err(e) => panic!("{}", e),
+    }
   }
 
-  fn to_nd(&self) -> Vec<usize> {
-    vec![*self]
+  /// Overflow-checked `required_len`, which also returns `None` if the ranks
+  /// of `self` and `stride` differ.
+  fn checked_required_len(&self, stride: &Self, offset: usize) -> Option<usize> {
+    span_len(self.as_slice(), stride.as_slice(), offset).ok()
   }
 
-  fn index_add(&self, shift: &Self) -> Self {
-    *self + *shift
+  /// Whether every offset reached by the shape `self` with the given stride
+  /// and base offset is less than `buf_len`. An empty shape always fits; a
+  /// stride of the wrong rank, or a span that overflows, never does.
+  fn fits_in(&self, stride: &Self, offset: usize, buf_len: usize) -> bool {
+    match span_len(self.as_slice(), stride.as_slice(), offset) {
+      Ok(len) => len <= buf_len,
+      Err(_) => false,
+    }
   }
 
-  fn index_sub(&self, shift: &Self) -> Self {
-    *self - *shift
+  /// Signed counterpart of `min_offset`; negative strides move the minimum
+  /// below the base offset, possibly below zero.
+  ///
+  /// Panics if the ranks of `self` and `stride` differ or the offset
+  /// overflows an `isize`.
+  fn min_offset_signed(&self, stride: &Self::Signed, offset: usize) -> Option<isize> {
+    match span_signed(self.as_slice(), stride.as_signed_slice(), offset) {
+      Ok(span) => span.map(|(lo, _)| lo),
+      Err(e) => panic!("{}", e),
+    }
   }
 
-  fn to_packed_stride(&self) -> Self {
-    1
+  /// Signed counterpart of `max_offset`.
+  fn max_offset_signed(&self, stride: &Self::Signed, offset: usize) -> Option<isize> {
+    match span_signed(self.as_slice(), stride.as_signed_slice(), offset) {
+      Ok(span) => span.map(|(_, hi)| hi),
+      Err(e) => panic!("{}", e),
+    }
   }
 
-  fn is_packed(&self, stride: &Self) -> bool {
-    self.to_packed_stride() == *stride
+  /// Signed counterpart of `fits_in`: also checks that no offset is negative.
+  fn fits_in_signed(&self, stride: &Self::Signed, offset: usize, buf_len: usize) -> bool {
+    match span_signed(self.as_slice(), stride.as_signed_slice(), offset) {
+      Ok(Some((lo, hi))) => lo >= 0 && (hi as usize) < buf_len,
+      Ok(None) => true,
+      Err(_) => false,
+    }
   }
 
-  fn index_prepend(&self, major: usize) -> Index2d {
-    [major, *self]
+  /// Overflow-checked `flat_len`.
+  fn checked_flat_len(&self) -> Option<usize> {
+    self.as_slice().iter().try_fold(1_usize, |len, &n| len.checked_mul(n))
   }
 
-  fn index_append(&self, minor: usize) -> Index2d {
-    [*self, minor]
+  /// Overflow-checked `flat_index`, which also returns `None` if the ranks
+  /// of `self` and `stride` differ.
+  fn checked_flat_index(&self, stride: &Self) -> Option<usize> {
+    if self.as_slice().len() != stride.as_slice().len() {
+      return None;
+    }
+    self.as_slice().iter().zip(stride.as_slice())
+      .try_fold(0_usize, |off, (&i, &s)| off.checked_add(i.checked_mul(s)?))
+  }
+
+  /// Overflow-checked `to_packed_stride_in`. Only the strides themselves
+  /// must fit in a `usize`, not the flat length of the shape.
+  fn checked_to_packed_stride_in(&self, layout: Layout) -> Option<Self> where Self: Sized {
+    let mut stride = self.clone();
+    {
+      let pairs = stride.as_mut_slice().iter_mut().zip(self.as_slice());
+      let mut acc = Some(1_usize);
+      let mut pack = |(s, &n): (&mut usize, &usize)| -> Option<()> {
+        *s = acc?;
+        acc = acc.and_then(|a| a.checked_mul(n));
+        Some(())
+      };
+      match layout {
+        Layout::ColumnMajor => pairs.map(&mut pack).collect::<Option<()>>()?,
+        Layout::RowMajor => pairs.rev().map(&mut pack).collect::<Option<()>>()?,
+      }
+    }
+    Some(stride)
   }
 
-  fn index_at(&self, axis: isize) -> usize {
-    assert_eq!(0, axis);
-    *self
+  /// Overflow-checked `to_packed_stride`.
+  fn checked_to_packed_stride(&self) -> Option<Self> where Self: Sized {
+    self.checked_to_packed_stride_in(Layout::ColumnMajor)
   }
 
-  fn index_cut(&self, axis: isize) -> Index0d {
-    assert_eq!(0, axis);
-    ()
+  /// Overflow-checked `index_add`, which also returns `None` if the ranks of
+  /// `self` and `shift` differ.
+  fn checked_index_add(&self, shift: &Self) -> Option<Self> where Self: Sized {
+    if self.as_slice().len() != shift.as_slice().len() {
+      return None;
+    }
+    let mut idx = self.clone();
+    for (i, &s) in idx.as_mut_slice().iter_mut().zip(shift.as_slice()) {
+      *i = i.checked_add(s)?;
+    }
+    Some(idx)
   }
 
-  fn flat_len(&self) -> usize {
-    *self
+  /// Underflow-checked `index_sub`, which also returns `None` if the ranks of
+  /// `self` and `shift` differ.
+  fn checked_index_sub(&self, shift: &Self) -> Option<Self> where Self: Sized {
+    if self.as_slice().len() != shift.as_slice().len() {
+      return None;
+    }
+    let mut idx = self.clone();
+    for (i, &s) in idx.as_mut_slice().iter_mut().zip(shift.as_slice()) {
+      *i = i.checked_sub(s)?;
+    }
+    Some(idx)
   }
+}
 
-  fn flat_index(&self, stride: &Self) -> usize {
-    (*self * *stride) as _
+fn to_isize(c: usize) -> isize {
+  match isize::try_from(c) {
+    Ok(c) => c,
+    Err(_) => panic!("index component {} overflows an isize", c),
   }
+}
 
-  fn inside(&self) -> usize {
-    *self
+fn to_signed_into(src: &[usize], dst: &mut [isize]) {
+  for (d, &c) in dst.iter_mut().zip(src) {
+    *d = to_isize(c);
   }
+}
 
-  fn outside(&self) -> usize {
-    *self
+fn unflat_into(shape: &[usize], mut offset: usize, idx: &mut [usize]) {
+  for (i, &s) in idx.iter_mut().zip(shape) {
+    if s == 0 {
+      *i = 0;
+      continue;
+    }
+    *i = offset % s;
+    offset /= s;
   }
+}
 
-  fn dim(&self) -> usize {
-    1
+fn check_span_rank(shape: &[usize], stride_dim: usize) -> Result<(), ArrayIdxError> {
+  if shape.len() != stride_dim {
+    return Err(ArrayIdxError::RankMismatch{expected: shape.len(), found: stride_dim});
   }
+  Ok(())
 }
 
-impl ArrayIndex for Index2d {
-  type Above = Index3d;
-  type Below = Index1d;
+/// The largest offset reached by the shape `shape` with the given stride and
+/// base offset, or `None` if the shape is empty.
+fn span_max(shape: &[usize], stride: &[usize], offset: usize) -> Result<Option<usize>, ArrayIdxError> {
+  check_span_rank(shape, stride.len())?;
+  if shape.contains(&0) {
+    return Ok(None);
+  }
+  shape.iter().zip(stride)
+    .try_fold(offset, |off, (&n, &s)| off.checked_add((n - 1).checked_mul(s)?))
+    .map(Some)
+    .ok_or(ArrayIdxError::Overflow)
+}
 
-  fn zero() -> Self {
-    [0, 0]
+fn span_len(shape: &[usize], stride: &[usize], offset: usize) -> Result<usize, ArrayIdxError> {
+  match span_max(shape, stride, offset)? {
+    Some(max) => max.checked_add(1).ok_or(ArrayIdxError::Overflow),
+    None => Ok(0),
   }
+}
 
-  fn from_nd(nd_shape: Vec<usize>) -> Self {
-    assert_eq!(2, nd_shape.len());
-    [ nd_shape[0],
-      nd_shape[1], ]
+/// The smallest and largest offsets reached by the shape `shape` with the
+/// given signed stride and base offset, or `None` if the shape is empty.
+fn span_signed(shape: &[usize], stride: &[isize], offset: usize) -> Result<Option<(isize, isize)>, ArrayIdxError> {
+  check_span_rank(shape, stride.len())?;
+  if shape.contains(&0) {
+    return Ok(None);
+  }
+  let offset = isize::try_from(offset).map_err(|_| ArrayIdxError::Overflow)?;
+  let (mut lo, mut hi) = (offset, offset);
+  for (&n, &s) in shape.iter().zip(stride) {
+    let disp = isize::try_from(n - 1).ok()
+      .and_then(|n| n.checked_mul(s))
+      .ok_or(ArrayIdxError::Overflow)?;
+    if disp < 0 {
+      lo = lo.checked_add(disp).ok_or(ArrayIdxError::Overflow)?;
+    } else {
+      hi = hi.checked_add(disp).ok_or(ArrayIdxError::Overflow)?;
+    }
   }
+  Ok(Some((lo, hi)))
+}
 
-  fn to_nd(&self) -> Vec<usize> {
-    (self as &[usize]).to_owned()
+impl IndexSlice for Index0d {
+  type Signed = SIndex0d;
+
+  fn as_slice(&self) -> &[usize] {
+    &[]
   }
 
-  fn index_add(&self, shift: &Self) -> Self {
-    [ self[0] + shift[0],
-      self[1] + shift[1], ]
+  fn as_mut_slice(&mut self) -> &mut [usize] {
+    &mut []
   }
 
-  fn index_sub(&self, shift: &Self) -> Self {
-    [ self[0] - shift[0],
-      self[1] - shift[1], ]
+  fn to_signed(&self) -> SIndex0d {
   }
+}
 
-  fn to_packed_stride(&self) -> Self {
-    let mut s = [0, 0];
-    s[0] = 1;
-    s[1] = s[0] * self[0];
-    s
+impl IndexSlice for Index1d {
+  type Signed = SIndex1d;
+
+  fn as_slice(&self) -> &[usize] {
+    slice::from_ref(self)
   }
 
-  fn is_packed(&self, stride: &Self) -> bool {
-    self.to_packed_stride() == *stride
+  fn as_mut_slice(&mut self) -> &mut [usize] {
+    slice::from_mut(self)
   }
 
-  fn index_prepend(&self, major: usize) -> Index3d {
-    [major, self[0], self[1]]
+  fn to_signed(&self) -> SIndex1d {
+    to_isize(*self)
   }
+}
+
+impl IndexSlice for IndexNd {
+  type Signed = SIndexNd;
 
-  fn index_append(&self, minor: usize) -> Index3d {
-    [self[0], self[1], minor]
+  fn as_slice(&self) -> &[usize] {
+    &self.components
   }
 
-  fn index_at(&self, axis: isize) -> usize {
-    self[axis as usize]
+  fn as_mut_slice(&mut self) -> &mut [usize] {
+    &mut self.components
   }
 
-  fn index_cut(&self, axis: isize) -> Index1d {
-    match axis {
-      0 => self[1],
-      1 => self[0],
-      _ => unreachable!(),
-    }
+  fn to_signed(&self) -> SIndexNd {
+    SIndexNd{components: self.components.iter().map(|&c| to_isize(c)).collect()}
   }
+}
 
-  fn flat_len(&self) -> usize {
-    self[0] * self[1]
+/// Maps an axis of an index of rank `ndim` to its position, with negative
+/// axes counting back from the last axis as in Python (`-1` is the outermost
+/// axis in the default column-major layout).
+pub fn normalize_axis(axis: isize, ndim: usize) -> Result<usize, ArrayIdxError> {
+  let pos = if axis < 0 { axis + ndim as isize } else { axis };
+  if pos < 0 || pos as usize >= ndim {
+    return Err(ArrayIdxError::AxisOutOfRange{axis, ndim});
   }
+  Ok(pos as usize)
+}
 
-  fn flat_index(&self, stride: &Self) -> usize {
-    ( self[0] * stride[0] +
-      self[1] * stride[1] ) as _
+/// Panicking counterpart of `normalize_axis`, used by the infallible APIs.
+fn unwrap_axis(axis: isize, ndim: usize) -> usize {
+  match normalize_axis(axis, ndim) {
+    Ok(pos) => pos,
+    Err(e) => panic!("{}", e),
   }
+}
 
-  fn inside(&self) -> usize {
-    self[0]
+pub trait ArrayIndex: IndexSlice + Clone + PartialEq + Eq + Hash + Debug {
+  fn zero() -> Self where Self: Sized;
+
+  fn from_nd(nd_shape: Vec<usize>) -> Self where Self: Sized;
+  /// Copy the components of a slice of the same rank, without allocating.
+  ///
+  /// Use this instead of `TryFrom<&[usize]>`, which cannot give a
+  /// `RankMismatch` error for the fixed ranks: std already implements it for
+  /// arrays (with a `TryFromSliceError` that carries no rank), and coherence
+  /// forbids implementing it for `usize`.
+  fn try_from_slice(components: &[usize]) -> Result<Self, ArrayIdxError> where Self: Sized {
+    let mut idx = Self::zero();
+    if components.len() != idx.dim() {
+      return Err(ArrayIdxError::RankMismatch{expected: idx.dim(), found: components.len()});
+    }
+    idx.as_mut_slice().copy_from_slice(components);
+    Ok(idx)
+  }
+  fn try_from_nd(nd_shape: Vec<usize>) -> Result<Self, ArrayIdxError> where Self: Sized {
+    let expected = Self::zero().dim();
+    if nd_shape.len() != expected {
+      return Err(ArrayIdxError::RankMismatch{expected, found: nd_shape.len()});
+    }
+    Ok(Self::from_nd(nd_shape))
+  }
+  fn to_nd(&self) -> Vec<usize>;
+  fn _to_nd(&self) -> IndexNd {
+    IndexNd{components: self.to_nd()}
   }
 
-  fn outside(&self) -> usize {
-    self[1]
+  fn index_add(&self, shift: &Self) -> Self where Self: Sized;
+  fn index_sub(&self, shift: &Self) -> Self where Self: Sized;
+
+  /// Negative axes count back from the last axis (see `normalize_axis`).
+  fn index_at(&self, axis: isize) -> usize;
+
+  fn try_index_at(&self, axis: isize) -> Result<usize, ArrayIdxError> {
+    let axis = normalize_axis(axis, self.dim())?;
+    Ok(self.index_at(axis as isize))
   }
 
-  fn dim(&self) -> usize {
-    2
+  fn to_packed_stride(&self) -> Self where Self: Sized;
+  fn is_packed(&self, stride: &Self) -> bool where Self: Sized;
+
+  fn flat_len(&self) -> usize;
+  fn flat_index(&self, stride: &Self) -> usize;
+
+  fn inside(&self) -> usize;
+  fn outside(&self) -> usize;
+
+  fn dim(&self) -> usize;
+  fn ndim(&self) -> usize {
+    self.dim()
   }
 }
 
-impl ArrayIndex for Index3d {
-  type Above = Index4d;
-  type Below = Index2d;
+/// Indices with a rank one higher than their own, which can gain an axis.
+pub trait HasAbove: ArrayIndex {
+  type Above: ArrayIndex + Sized;
 
-  fn zero() -> Self {
-    [0, 0, 0]
-  }
+  fn index_prepend(&self, new_inside: usize) -> Self::Above;
+  fn index_append(&self, new_outside: usize) -> Self::Above;
 
-  fn from_nd(nd_shape: Vec<usize>) -> Self {
-    assert_eq!(3, nd_shape.len());
-    [ nd_shape[0],
-      nd_shape[1],
-      nd_shape[2], ]
+  fn stride_append_packed(&self, outside: usize) -> Self::Above where Self: Sized {
+    self.index_append(self.outside() * outside)
   }
+  /// Row-major counterpart of `stride_append_packed`: the new outermost axis
+  /// is prepended as axis 0.
+  fn stride_prepend_packed_c(&self, outside: usize) -> Self::Above where Self: Sized {
+    self.index_prepend(self.outside_c() * outside)
+  }
+}
 
-  fn to_nd(&self) -> Vec<usize> {
-    (self as &[usize]).to_owned()
+/// Indices with a rank one lower than their own, which can lose an axis.
+pub trait HasBelow: ArrayIndex {
+  type Below: ArrayIndex + Sized;
+
+  /// Negative axes count back from the last axis (see `normalize_axis`).
+  fn index_cut(&self, axis: isize) -> Self::Below;
+
+  fn try_index_cut(&self, axis: isize) -> Result<Self::Below, ArrayIdxError> {
+    let axis = normalize_axis(axis, self.dim())?;
+    Ok(self.index_cut(axis as isize))
   }
+}
 
-  fn index_add(&self, shift: &Self) -> Self {
-    [ self[0] + shift[0],
-      self[1] + shift[1],
-      self[2] + shift[2], ]
+impl ArrayIndex for Index0d {
+  fn zero() -> Self {
   }
 
-  fn index_sub(&self, shift: &Self) -> Self {
-    [ self[0] - shift[0],
-      self[1] - shift[1],
-      self[2] - shift[2], ]
+  fn from_nd(nd_shape: Vec<usize>) -> Self {
+    assert_eq!(0, nd_shape.len());
   }
 
-  fn to_packed_stride(&self) -> Self {
-    let mut s = [0, 0, 0];
-    s[0] = 1;
-    s[1] = s[0] * self[0];
-    s[2] = s[1] * self[1];
-    s
+  fn to_nd(&self) -> Vec<usize> {
+    vec![]
   }
 
-  fn is_packed(&self, stride: &Self) -> bool {
-    self.to_packed_stride() == *stride
+  fn index_add(&self, _shift: &Self) -> Self {
   }
 
-  fn index_prepend(&self, major: usize) -> Index4d {
-    [major, self[0], self[1], self[2]]
+  fn index_sub(&self, _shift: &Self) -> Self {
   }
 
-  fn index_append(&self, minor: usize) -> Index4d {
-    [self[0], self[1], self[2], minor]
+  fn to_packed_stride(&self) -> Self {
   }
 
-  fn index_at(&self, axis: isize) -> usize {
-    self[axis as usize]
+  fn is_packed(&self, _stride: &Self) -> bool {
+    true
   }
 
-  fn index_cut(&self, axis: isize) -> Index2d {
-    match axis {
-      0 => [self[1], self[2]],
-      1 => [self[0], self[2]],
-      2 => [self[0], self[1]],
-      _ => unreachable!(),
-    }
+  fn index_at(&self, axis: isize) -> usize {
+    unwrap_axis(axis, 0);
+    unreachable!();
   }
 
   fn flat_len(&self) -> usize {
-    self[0] * self[1] * self[2]
+    1
   }
 
-  fn flat_index(&self, stride: &Self) -> usize {
-    ( self[0] * stride[0] +
-      self[1] * stride[1] +
-      self[2] * stride[2] ) as _
+  fn flat_index(&self, _stride: &Self) -> usize {
+    0
   }
 
   fn inside(&self) -> usize {
-    self[0]
+    1
   }
 
   fn outside(&self) -> usize {
-    self[2]
+    1
   }
 
   fn dim(&self) -> usize {
-    3
+    0
   }
 }
 
-impl ArrayIndex for Index4d {
-  type Above = Index5d;
-  type Below = Index3d;
+impl ArrayIndex for Index1d {
+  fn zero() -> Self {
+    0
+  }
+
+  fn from_nd(nd_shape: Vec<usize>) -> Self {
+    assert_eq!(1, nd_shape.len());
+    nd_shape[0]
+  }
+
+  fn to_nd(&self) -> Vec<usize> {
+    vec![*self]
+  }
 
   fn index_add(&self, shift: &Self) -> Self {
-    [ self[0] + shift[0],
-      self[1] + shift[1],
-      self[2] + shift[2],
-      self[3] + shift[3], ]
+    *self + *shift
   }
 
   fn index_sub(&self, shift: &Self) -> Self {
-    [ self[0] - shift[0],
-      self[1] - shift[1],
-      self[2] - shift[2],
-      self[3] - shift[3], ]
+    *self - *shift
   }
 
   fn to_packed_stride(&self) -> Self {
-    let mut s = [0, 0, 0, 0];
-    s[0] = 1;
-    s[1] = s[0] * self[0];
-    s[2] = s[1] * self[1];
-    s[3] = s[2] * self[2];
-    s
+    1
   }
 
-  fn zero() -> Self {
-    [0, 0, 0, 0]
+  fn is_packed(&self, stride: &Self) -> bool {
+    self.to_packed_stride() == *stride
   }
 
-  fn from_nd(nd_shape: Vec<usize>) -> Self {
-    assert_eq!(4, nd_shape.len());
-    [ nd_shape[0],
-      nd_shape[1],
-      nd_shape[2],
-      nd_shape[3], ]
+  fn index_at(&self, axis: isize) -> usize {
+    unwrap_axis(axis, 1);
+    *self
   }
 
-  fn to_nd(&self) -> Vec<usize> {
-    (self as &[usize]).to_owned()
+  fn flat_len(&self) -> usize {
+    *self
   }
 
-  fn is_packed(&self, stride: &Self) -> bool {
-    self.to_packed_stride() == *stride
+  fn flat_index(&self, stride: &Self) -> usize {
+    (*self * *stride) as _
   }
 
-  fn index_prepend(&self, major: usize) -> Index5d {
-    [major, self[0], self[1], self[2], self[3]]
+  fn inside(&self) -> usize {
+    *self
   }
 
-  fn index_append(&self, minor: usize) -> Index5d {
-    [self[0], self[1], self[2], self[3], minor]
+  fn outside(&self) -> usize {
+    *self
   }
 
-  fn index_at(&self, axis: isize) -> usize {
-    self[axis as usize]
+  fn dim(&self) -> usize {
+    1
   }
+}
 
-  fn index_cut(&self, axis: isize) -> Index3d {
-    match axis {
-      0 => [self[1], self[2], self[3]],
-      1 => [self[0], self[2], self[3]],
-      2 => [self[0], self[1], self[3]],
-      3 => [self[0], self[1], self[2]],
-      _ => unreachable!(),
-    }
+impl HasAbove for Index0d {
+  type Above = Index1d;
+
+  fn index_prepend(&self, major: usize) -> Index1d {
+    major
   }
 
-  fn flat_len(&self) -> usize {
-    self[0] * self[1] * self[2] * self[3]
+  fn index_append(&self, minor: usize) -> Index1d {
+    minor
   }
+}
 
-  fn flat_index(&self, stride: &Self) -> usize {
-    ( self[0] * stride[0] +
-      self[1] * stride[1] +
-      self[2] * stride[2] +
-      self[3] * stride[3] ) as _
+impl HasAbove for Index1d {
+  type Above = Index2d;
+
+  fn index_prepend(&self, major: usize) -> Index2d {
+    [major, *self]
   }
 
-  fn inside(&self) -> usize {
-    self[0]
+  fn index_append(&self, minor: usize) -> Index2d {
+    [*self, minor]
   }
+}
 
-  fn outside(&self) -> usize {
-    self[3]
+impl HasBelow for Index1d {
+  type Below = Index0d;
+
+  fn index_cut(&self, axis: isize) -> Index0d {
+    unwrap_axis(axis, 1);
   }
+}
 
-  fn dim(&self) -> usize {
-    4
+// The fixed ranks from 2 up are plain arrays, so they share one impl per
+// trait; only the rank-changing `HasAbove`/`HasBelow` impls are per-rank.
+
+impl<const N: usize> SIndexSlice for [isize; N] {
+  fn as_signed_slice(&self) -> &[isize] {
+    self
+  }
+
+  fn as_mut_signed_slice(&mut self) -> &mut [isize] {
+    self
   }
 }
 
-impl ArrayIndex for Index5d {
-  type Above = UnimplIndex;
-  type Below = Index4d;
+impl<const N: usize> IndexSlice for [usize; N] {
+  type Signed = [isize; N];
+
+  fn as_slice(&self) -> &[usize] {
+    self
+  }
 
+  fn as_mut_slice(&mut self) -> &mut [usize] {
+    self
+  }
+
+  fn to_signed(&self) -> [isize; N] {
+    let mut s = [0; N];
+    to_signed_into(self, &mut s);
+    s
+  }
+}
+
+impl<const N: usize> ArrayIndex for [usize; N] {
   fn zero() -> Self {
-    [0, 0, 0, 0, 0]
+    [0; N]
   }
 
   fn from_nd(nd_shape: Vec<usize>) -> Self {
-    assert_eq!(5, nd_shape.len());
-    [ nd_shape[0],
-      nd_shape[1],
-      nd_shape[2],
-      nd_shape[3],
-      nd_shape[4], ]
+    assert_eq!(N, nd_shape.len());
+    let mut idx = [0; N];
+    idx.copy_from_slice(&nd_shape);
+    idx
   }
 
   fn to_nd(&self) -> Vec<usize> {
@@ -581,28 +975,26 @@ impl ArrayIndex for Index5d {
   }
 
   fn index_add(&self, shift: &Self) -> Self {
-    [ self[0] + shift[0],
-      self[1] + shift[1],
-      self[2] + shift[2],
-      self[3] + shift[3],
-      self[4] + shift[4], ]
+    let mut idx = *self;
+    for (i, &s) in idx.iter_mut().zip(shift.iter()) {
+      *i += s;
+    }
+    idx
   }
 
   fn index_sub(&self, shift: &Self) -> Self {
-    [ self[0] - shift[0],
-      self[1] - shift[1],
-      self[2] - shift[2],
-      self[3] - shift[3],
-      self[4] - shift[4], ]
+    let mut idx = *self;
+    for (i, &s) in idx.iter_mut().zip(shift.iter()) {
+      *i -= s;
+    }
+    idx
   }
 
   fn to_packed_stride(&self) -> Self {
-    let mut s = [0, 0, 0, 0, 0];
-    s[0] = 1;
-    s[1] = s[0] * self[0];
-    s[2] = s[1] * self[1];
-    s[3] = s[2] * self[2];
-    s[4] = s[3] * self[3];
+    let mut s = [1; N];
+    for d in 1 .. N {
+      s[d] = s[d-1] * self[d-1];
+    }
     s
   }
 
@@ -610,141 +1002,281 @@ impl ArrayIndex for Index5d {
     self.to_packed_stride() == *stride
   }
 
-  fn index_prepend(&self, major: usize) -> UnimplIndex {
-    unimplemented!();
-  }
-
-  fn index_append(&self, minor: usize) -> UnimplIndex {
-    unimplemented!();
-  }
-
   fn index_at(&self, axis: isize) -> usize {
-    self[axis as usize]
-  }
-
-  fn index_cut(&self, axis: isize) -> Index4d {
-    match axis {
-      0 => [self[1], self[2], self[3], self[4]],
-      1 => [self[0], self[2], self[3], self[4]],
-      2 => [self[0], self[1], self[3], self[4]],
-      3 => [self[0], self[1], self[2], self[4]],
-      4 => [self[0], self[1], self[2], self[3]],
-      _ => unreachable!(),
-    }
+    self[unwrap_axis(axis, N)]
   }
 
   fn flat_len(&self) -> usize {
-    self[0] * self[1] * self[2] * self[3] * self[4]
+    self.iter().product()
   }
 
   fn flat_index(&self, stride: &Self) -> usize {
-    ( self[0] * stride[0] +
-      self[1] * stride[1] +
-      self[2] * stride[2] +
-      self[3] * stride[3] +
-      self[4] * stride[4] ) as _
+    self.iter().zip(stride.iter())
+      .map(|(&i, &s)| i * s)
+      .sum()
   }
 
   fn inside(&self) -> usize {
-    self[0]
+    self.first().cloned().unwrap_or(1)
   }
 
   fn outside(&self) -> usize {
-    self[4]
+    self.last().cloned().unwrap_or(1)
   }
 
   fn dim(&self) -> usize {
-    5
+    N
   }
 }
 
-impl ArrayIndex for UnimplIndex {
-  type Above = UnimplIndex;
-  type Below = UnimplIndex;
+impl<const N: usize> From<[usize; N]> for IndexNd {
+  fn from(idx: [usize; N]) -> Self {
+    IndexNd{components: idx.to_vec()}
+  }
+}
+
+// For conversions from `&[usize]`, see `ArrayIndex::try_from_slice`.
+impl<const N: usize> TryFrom<IndexNd> for [usize; N] {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: IndexNd) -> Result<Self, ArrayIdxError> {
+    <[usize; N] as ArrayIndex>::try_from_slice(&idx.components)
+  }
+}
+
+macro_rules! impl_has_above {
+  ($idx:ident, $n:expr, $above:ident) => {
+    impl HasAbove for $idx {
+      type Above = $above;
+
+      fn index_prepend(&self, major: usize) -> $above {
+        let mut idx = [0; $n + 1];
+        idx[0] = major;
+        idx[1 ..].copy_from_slice(self);
+        idx
+      }
+
+      fn index_append(&self, minor: usize) -> $above {
+        let mut idx = [0; $n + 1];
+        idx[.. $n].copy_from_slice(self);
+        idx[$n] = minor;
+        idx
+      }
+    }
+  };
+}
 
+macro_rules! impl_has_below {
+  ($idx:ident, $n:expr, $below:ident) => {
+    impl HasBelow for $idx {
+      type Below = $below;
+
+      fn index_cut(&self, axis: isize) -> $below {
+        let axis = unwrap_axis(axis, $n);
+        let mut idx = <$below as ArrayIndex>::zero();
+        {
+          let components = idx.as_mut_slice();
+          components[.. axis].copy_from_slice(&self[.. axis]);
+          components[axis ..].copy_from_slice(&self[axis + 1 ..]);
+        }
+        idx
+      }
+    }
+  };
+}
+
+impl_has_above!(Index2d, 2, Index3d);
+impl_has_above!(Index3d, 3, Index4d);
+impl_has_above!(Index4d, 4, Index5d);
+impl_has_above!(Index5d, 5, Index6d);
+impl_has_above!(Index6d, 6, Index7d);
+impl_has_above!(Index7d, 7, Index8d);
+
+impl_has_below!(Index2d, 2, Index1d);
+impl_has_below!(Index3d, 3, Index2d);
+impl_has_below!(Index4d, 4, Index3d);
+impl_has_below!(Index5d, 5, Index4d);
+impl_has_below!(Index6d, 6, Index5d);
+impl_has_below!(Index7d, 7, Index6d);
+impl_has_below!(Index8d, 8, Index7d);
+
+/// A rank-0 `IndexNd` stands in for `zero()`, since the trait does not know
+/// the rank, so generic code must not assume that `I::zero()` has the rank of
+/// its other indices. Rank-changing operations keep the type `IndexNd`.
+impl ArrayIndex for IndexNd {
   fn zero() -> Self {
-    unimplemented!();
+    IndexNd::default()
   }
 
   fn from_nd(nd_shape: Vec<usize>) -> Self {
-    unimplemented!();
+    IndexNd{components: nd_shape}
+  }
+
+  fn try_from_slice(components: &[usize]) -> Result<Self, ArrayIdxError> {
+    Ok(IndexNd{components: components.to_vec()})
+  }
+
+  fn try_from_nd(nd_shape: Vec<usize>) -> Result<Self, ArrayIdxError> {
+    Ok(IndexNd{components: nd_shape})
   }
 
   fn to_nd(&self) -> Vec<usize> {
-    unimplemented!();
+    self.components.clone()
   }
 
   fn index_add(&self, shift: &Self) -> Self {
-    unimplemented!();
+    assert_eq!(self.dim(), shift.dim());
+    IndexNd{components: self.components.iter().zip(shift.components.iter())
+      .map(|(&x, &y)| x + y)
+      .collect()}
   }
 
   fn index_sub(&self, shift: &Self) -> Self {
-    unimplemented!();
+    assert_eq!(self.dim(), shift.dim());
+    IndexNd{components: self.components.iter().zip(shift.components.iter())
+      .map(|(&x, &y)| x - y)
+      .collect()}
   }
 
   fn to_packed_stride(&self) -> Self {
-    unimplemented!();
+    IndexNd::to_packed_stride(self)
   }
 
   fn is_packed(&self, stride: &Self) -> bool {
-    unimplemented!();
-  }
-
-  fn index_prepend(&self, major: usize) -> UnimplIndex {
-    unimplemented!();
-  }
-
-  fn index_append(&self, minor: usize) -> UnimplIndex {
-    unimplemented!();
+    IndexNd::is_packed(self, stride)
   }
 
   fn index_at(&self, axis: isize) -> usize {
-    unimplemented!();
-  }
-
-  fn index_cut(&self, axis: isize) -> UnimplIndex {
-    unimplemented!();
+    IndexNd::index_at(self, axis)
   }
 
   fn flat_len(&self) -> usize {
-    unimplemented!();
+    IndexNd::flat_len(self)
   }
 
   fn flat_index(&self, stride: &Self) -> usize {
-    unimplemented!();
+    assert_eq!(self.dim(), stride.dim());
+    self.components.iter().zip(stride.components.iter())
+      .map(|(&i, &s)| i * s)
+      .sum()
   }
 
   fn inside(&self) -> usize {
-    unimplemented!();
+    IndexNd::inside(self)
   }
 
   fn outside(&self) -> usize {
-    unimplemented!();
+    IndexNd::outside(self)
   }
 
   fn dim(&self) -> usize {
-    unimplemented!();
+    IndexNd::dim(self)
+  }
+}
+
+impl HasAbove for IndexNd {
+  type Above = IndexNd;
+
+  fn index_prepend(&self, major: usize) -> IndexNd {
+    let mut components = Vec::with_capacity(self.dim() + 1);
+    components.push(major);
+    components.extend_from_slice(&self.components);
+    IndexNd{components}
+  }
+
+  fn index_append(&self, minor: usize) -> IndexNd {
+    let mut components = Vec::with_capacity(self.dim() + 1);
+    components.extend_from_slice(&self.components);
+    components.push(minor);
+    IndexNd{components}
+  }
+}
+
+impl HasBelow for IndexNd {
+  type Below = IndexNd;
+
+  fn index_cut(&self, axis: isize) -> IndexNd {
+    let mut components = self.components.clone();
+    components.remove(unwrap_axis(axis, self.dim()));
+    IndexNd{components}
+  }
+}
+
+impl From<Index0d> for IndexNd {
+  fn from(_idx: Index0d) -> Self {
+    IndexNd{components: vec![]}
+  }
+}
+
+impl TryFrom<IndexNd> for Index0d {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: IndexNd) -> Result<Self, ArrayIdxError> {
+    <Index0d as ArrayIndex>::try_from_slice(&idx.components)
+  }
+}
+
+impl From<Index1d> for IndexNd {
+  fn from(idx: Index1d) -> Self {
+    IndexNd{components: vec![idx]}
+  }
+}
+
+impl TryFrom<IndexNd> for Index1d {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: IndexNd) -> Result<Self, ArrayIdxError> {
+    <Index1d as ArrayIndex>::try_from_slice(&idx.components)
   }
 }
 
 pub fn range2idxs_1d<R>(r: R, size: usize) -> (usize, usize)
 where R: RangeBounds<usize>,
+{
+  match try_range2idxs_1d(r, size) {
+    Ok(idxs) => idxs,
+    Err(e) => panic!("{}", e),
+  }
+}
+
+pub fn try_range2idxs_1d<R>(r: R, size: usize) -> Result<(usize, usize), ArrayIdxError>
+where R: RangeBounds<usize>,
 {
   let start_idx = match r.start_bound() {
     Bound::Included(&x) => x,
-    Bound::Excluded(&x) => x + 1,
+    Bound::Excluded(&x) => x.checked_add(1).ok_or(ArrayIdxError::Overflow)?,
     Bound::Unbounded => 0,
   };
   let end_idx = match r.end_bound() {
-    Bound::Included(&x) => x + 1,
+    Bound::Included(&x) => x.checked_add(1).ok_or(ArrayIdxError::Overflow)?,
     Bound::Excluded(&x) => x,
     Bound::Unbounded => size,
   };
-  assert!(start_idx <= end_idx,
-      "array bounds violation: {} greater than {}", start_idx, end_idx);
-  assert!(end_idx <= size,
-      "array end bounds violation: {} greater than {}", end_idx, size);
-  (start_idx, end_idx)
+  if start_idx > end_idx {
+    return Err(ArrayIdxError::InvertedRange{start: start_idx, end: end_idx});
+  }
+  if end_idx > size {
+    return Err(ArrayIdxError::OutOfBounds{index: end_idx, size});
+  }
+  Ok((start_idx, end_idx))
+}
+
+/// Normalizes a range with a nonzero step into the first selected index and
+/// the number of selected indices. A positive step walks forward from the
+/// start of the range; a negative step walks backward from its last index,
+/// so e.g. `(.., -1)` selects every index in reverse.
+pub fn range2idxs_step_1d<R>(r: R, step: isize, size: usize) -> (usize, usize)
+where R: RangeBounds<usize>,
+{
+  assert!(step != 0, "slice step cannot be zero");
+  let (start_idx, end_idx) = range2idxs_1d(r, size);
+  let (span, abs_step) = (end_idx - start_idx, step.unsigned_abs());
+  let len = span / abs_step + (span % abs_step != 0) as usize;
+  if len == 0 {
+    return (start_idx, 0);
+  }
+  let first_idx = if step > 0 { start_idx } else { end_idx - 1 };
+  (first_idx, len)
 }
 
 /*pub fn unzip_range_3d<RR>(rr: RR, size: [usize; 3]) -> (Range<usize>, Range<usize>, Range<usize>)
@@ -753,41 +1285,80 @@ where RR: RangeBounds<[usize; 3]>
   // TODO
 }*/
 
-pub fn range2idxs_2d<R0, R1>(r0: R0, r1: R1, size: [usize; 2]) -> ([usize; 2], [usize; 2])
-where R0: RangeBounds<usize>,
-      R1: RangeBounds<usize>,
-{
-  let (s0, e0) = range2idxs_1d(r0, size[0]);
-  let (s1, e1) = range2idxs_1d(r1, size[1]);
-  let start_idx = [s0, s1];
-  let end_idx = [e0, e1];
-  (start_idx, end_idx)
+macro_rules! impl_range2idxs {
+  ($name:ident, $try_name:ident, $n:expr; $($r:ident: $R:ident: $d:tt),*) => {
+    #[allow(clippy::too_many_arguments)]
+    pub fn $name<$($R),*>($($r: $R,)* size: [usize; $n]) -> ([usize; $n], [usize; $n])
+    where $($R: RangeBounds<usize>),*
+    {
+      match $try_name($($r,)* size) {
+        Ok(idxs) => idxs,
+        Err(e) => panic!("{}", e),
+      }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn $try_name<$($R),*>($($r: $R,)* size: [usize; $n]) -> Result<([usize; $n], [usize; $n]), ArrayIdxError>
+    where $($R: RangeBounds<usize>),*
+    {
+      let mut start_idx = [0; $n];
+      let mut end_idx = [0; $n];
+      $(
+        let (s, e) = try_range2idxs_1d($r, size[$d])?;
+        start_idx[$d] = s;
+        end_idx[$d] = e;
+      )*
+      Ok((start_idx, end_idx))
+    }
+  };
 }
 
-pub fn range2idxs_3d<R0, R1, R2>(r0: R0, r1: R1, r2: R2, size: [usize; 3]) -> ([usize; 3], [usize; 3])
-where R0: RangeBounds<usize>,
-      R1: RangeBounds<usize>,
-      R2: RangeBounds<usize>,
-{
-  let (s0, e0) = range2idxs_1d(r0, size[0]);
-  let (s1, e1) = range2idxs_1d(r1, size[1]);
-  let (s2, e2) = range2idxs_1d(r2, size[2]);
-  let start_idx = [s0, s1, s2];
-  let end_idx = [e0, e1, e2];
-  (start_idx, end_idx)
-}
-
-pub fn range2idxs_4d<R0, R1, R2, R3>(r0: R0, r1: R1, r2: R2, r3: R3, size: [usize; 4]) -> ([usize; 4], [usize; 4])
-where R0: RangeBounds<usize>,
-      R1: RangeBounds<usize>,
-      R2: RangeBounds<usize>,
-      R3: RangeBounds<usize>,
-{
-  let (s0, e0) = range2idxs_1d(r0, size[0]);
-  let (s1, e1) = range2idxs_1d(r1, size[1]);
-  let (s2, e2) = range2idxs_1d(r2, size[2]);
-  let (s3, e3) = range2idxs_1d(r3, size[3]);
-  let start_idx = [s0, s1, s2, s3];
-  let end_idx = [e0, e1, e2, e3];
-  (start_idx, end_idx)
+impl_range2idxs!(range2idxs_2d, try_range2idxs_2d, 2;
+    r0: R0: 0, r1: R1: 1);
+impl_range2idxs!(range2idxs_3d, try_range2idxs_3d, 3;
+    r0: R0: 0, r1: R1: 1, r2: R2: 2);
+impl_range2idxs!(range2idxs_4d, try_range2idxs_4d, 4;
+    r0: R0: 0, r1: R1: 1, r2: R2: 2, r3: R3: 3);
+impl_range2idxs!(range2idxs_5d, try_range2idxs_5d, 5;
+    r0: R0: 0, r1: R1: 1, r2: R2: 2, r3: R3: 3, r4: R4: 4);
+impl_range2idxs!(range2idxs_6d, try_range2idxs_6d, 6;
+    r0: R0: 0, r1: R1: 1, r2: R2: 2, r3: R3: 3, r4: R4: 4, r5: R5: 5);
+impl_range2idxs!(range2idxs_7d, try_range2idxs_7d, 7;
+    r0: R0: 0, r1: R1: 1, r2: R2: 2, r3: R3: 3, r4: R4: 4, r5: R5: 5, r6: R6: 6);
+impl_range2idxs!(range2idxs_8d, try_range2idxs_8d, 8;
+    r0: R0: 0, r1: R1: 1, r2: R2: 2, r3: R3: 3, r4: R4: 4, r5: R5: 5, r6: R6: 6, r7: R7: 7);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check_nd_matches_fixed<const N: usize>(shape: [usize; N], idx: [usize; N], shift: [usize; N])
+  where [usize; N]: HasAbove + HasBelow,
+        <[usize; N] as HasAbove>::Above: Into<IndexNd>,
+        <[usize; N] as HasBelow>::Below: Into<IndexNd>,
+  {
+    let nd_shape = IndexNd::from(shape);
+    let nd_idx = IndexNd::from(idx);
+    let nd_shift = IndexNd::from(shift);
+    assert_eq!(ArrayIndex::index_add(&nd_idx, &nd_shift), idx.index_add(&shift).into());
+    let sum = idx.index_add(&shift);
+    assert_eq!(ArrayIndex::index_sub(&IndexNd::from(sum), &nd_shift), sum.index_sub(&shift).into());
+    assert_eq!(nd_idx.index_prepend(7), idx.index_prepend(7).into());
+    assert_eq!(nd_idx.index_append(7), idx.index_append(7).into());
+    for axis in -(N as isize) .. N as isize {
+      assert_eq!(nd_idx.index_cut(axis), idx.index_cut(axis).into());
+    }
+    let stride = shape.to_packed_stride();
+    assert_eq!(ArrayIndex::to_packed_stride(&nd_shape), stride.into());
+    assert_eq!(ArrayIndex::flat_index(&nd_idx, &IndexNd::from(stride)), idx.flat_index(&stride));
+    assert_eq!(ArrayIndex::flat_index(&nd_idx, &nd_shift), idx.flat_index(&shift));
+  }
+
+  #[test]
+  fn index_nd_matches_fixed_rank() {
+    check_nd_matches_fixed([2, 3], [1, 2], [3, 1]);
+    check_nd_matches_fixed([4, 1, 5], [3, 0, 4], [0, 2, 1]);
+    check_nd_matches_fixed([3, 2, 2, 7], [2, 1, 0, 6], [1, 1, 1, 1]);
+    check_nd_matches_fixed([0, 5, 2, 1, 3], [0, 4, 1, 0, 2], [5, 0, 3, 2, 9]);
+  }
 }
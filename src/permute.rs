@@ -0,0 +1,97 @@
+use {IndexSlice, ViewDesc, SViewDesc, SIndexSlice};
+
+use std::ops::{Index};
+
+/// A validated permutation of the axes `0 .. dim`. Permuting an index by
+/// `perm` moves axis `perm[i]` of the input to axis `i` of the output, as in
+/// NumPy's `transpose(axes)`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Permutation {
+  axes: Vec<usize>,
+}
+
+impl Index<usize> for Permutation {
+  type Output = usize;
+
+  fn index(&self, index: usize) -> &usize {
+    &self.axes[index]
+  }
+}
+
+impl Permutation {
+  /// Returns `None` unless `axes` contains each of `0 .. axes.len()` exactly
+  /// once.
+  pub fn new(axes: Vec<usize>) -> Option<Self> {
+    let mut seen = vec![false; axes.len()];
+    for &a in axes.iter() {
+      if a >= seen.len() || seen[a] {
+        return None;
+      }
+      seen[a] = true;
+    }
+    Some(Permutation{axes})
+  }
+
+  pub fn identity(dim: usize) -> Self {
+    Permutation{axes: (0 .. dim).collect()}
+  }
+
+  pub fn dim(&self) -> usize {
+    self.axes.len()
+  }
+
+  pub fn axes(&self) -> &[usize] {
+    &self.axes
+  }
+
+  pub fn is_identity(&self) -> bool {
+    self.axes.iter().enumerate().all(|(i, &a)| i == a)
+  }
+
+  /// The permutation that undoes `self`.
+  pub fn inverse(&self) -> Self {
+    let mut axes = vec![0; self.dim()];
+    for (i, &a) in self.axes.iter().enumerate() {
+      axes[a] = i;
+    }
+    Permutation{axes}
+  }
+
+  /// The permutation equivalent to permuting by `self` and then by `then`,
+  /// i.e. `x.permute_axes(&p.compose(&q)) == x.permute_axes(&p).permute_axes(&q)`.
+  pub fn compose(&self, then: &Permutation) -> Self {
+    assert_eq!(self.dim(), then.dim());
+    Permutation{axes: then.axes.iter().map(|&a| self.axes[a]).collect()}
+  }
+}
+
+pub(crate) fn permute_into<T: Copy>(src: &[T], perm: &Permutation, dst: &mut [T]) {
+  assert_eq!(src.len(), perm.dim(),
+      "permutation of {} axes applied to an index of rank {}", perm.dim(), src.len());
+  for (d, &a) in dst.iter_mut().zip(perm.axes.iter()) {
+    *d = src[a];
+  }
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  /// Transpose the view by applying `perm` to both its shape and stride.
+  pub fn permute_axes(&self, perm: &Permutation) -> Self {
+    ViewDesc{
+      shape: self.shape.permute_axes(perm),
+      stride: self.stride.permute_axes(perm),
+      offset: self.offset,
+    }
+  }
+}
+
+impl<I: IndexSlice> SViewDesc<I> {
+  pub fn permute_axes(&self, perm: &Permutation) -> Self {
+    let mut stride = self.stride.clone();
+    permute_into(self.stride.as_signed_slice(), perm, stride.as_mut_signed_slice());
+    SViewDesc{
+      shape: self.shape.permute_axes(perm),
+      stride,
+      offset: self.offset,
+    }
+  }
+}
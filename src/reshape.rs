@@ -0,0 +1,188 @@
+use {IndexSlice, IndexNd, Layout, ViewDesc};
+
+/// Attempt to reinterpret the strided layout `(shape, stride)` as having
+/// shape `new_shape` without copying, like NumPy's no-copy reshape. Returns
+/// the stride for `new_shape`, or `None` if the flat lengths differ or the
+/// elements are not laid out compatibly (in column-major order) for the new
+/// shape.
+/// Overflowing lengths or strides also give `None`.
+pub fn try_reshape<I: IndexSlice, O: IndexSlice>(shape: &I, stride: &I, new_shape: &O) -> Option<O> {
+  let len = checked_len(shape)?;
+  let new_len = checked_len(new_shape)?;
+  if len != new_len {
+    return None;
+  }
+  if len == 0 {
+    return new_shape.checked_to_packed_stride_in(Layout::ColumnMajor);
+  }
+  // Size-1 axes do not constrain the layout.
+  let old: Vec<(usize, usize)> = shape.as_slice().iter().cloned()
+    .zip(stride.as_slice().iter().cloned())
+    .filter(|&(n, _)| n != 1)
+    .collect();
+  let new = new_shape.as_slice();
+  let mut new_stride = new_shape.clone();
+  {
+    let ns = new_stride.as_mut_slice();
+    let (mut oi, mut ni) = (0, 0);
+    while oi < old.len() && ni < new.len() {
+      // Find the smallest groups of old and new axes with equal sizes.
+      let (mut oj, mut nj) = (oi + 1, ni + 1);
+      let (mut op, mut np) = (old[oi].0, new[ni]);
+      while op != np {
+        if np < op {
+          np *= new[nj];
+          nj += 1;
+        } else {
+          op *= old[oj].0;
+          oj += 1;
+        }
+      }
+      // The old group must be contiguous to be merged or split.
+      for ok in oi .. oj - 1 {
+        if old[ok + 1].1 != old[ok].0 * old[ok].1 {
+          return None;
+        }
+      }
+      ns[ni] = old[oi].1;
+      for nk in ni + 1 .. nj {
+        ns[nk] = ns[nk - 1].checked_mul(new[nk - 1])?;
+      }
+      oi = oj;
+      ni = nj;
+    }
+    // Any remaining new axes have size 1.
+    for nk in ni .. new.len() {
+      ns[nk] = match nk {
+        0 => 1,
+        _ => ns[nk - 1].checked_mul(new[nk - 1])?,
+      };
+    }
+  }
+  Some(new_stride)
+}
+
+/// Resolve a NumPy-style shape in which at most one dimension is `-1`, to be
+/// inferred so that the shape has the given flat length. Returns `None` if
+/// there is more than one `-1`, another negative dimension, no size fits, or
+/// the known dimensions overflow a `usize`.
+// `usize::is_multiple_of` would need Rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn infer_shape(flat_len: usize, dims: &[isize]) -> Option<IndexNd> {
+  let mut shape = IndexNd::zero(dims.len());
+  let mut infer_axis = None;
+  let mut known_len = Some(1_usize);
+  for (d, &n) in dims.iter().enumerate() {
+    if n == -1 {
+      if infer_axis.is_some() {
+        return None;
+      }
+      infer_axis = Some(d);
+    } else if n < 0 {
+      return None;
+    } else {
+      shape.components[d] = n as usize;
+      known_len = known_len.and_then(|len| len.checked_mul(n as usize));
+    }
+  }
+  let known_len = match known_len {
+    Some(len) => len,
+    None if dims.contains(&0) => 0,
+    None => return None,
+  };
+  match infer_axis {
+    None => {
+      if known_len != flat_len {
+        return None;
+      }
+    }
+    Some(d) => {
+      if known_len == 0 || flat_len % known_len != 0 {
+        return None;
+      }
+      shape.components[d] = flat_len / known_len;
+    }
+  }
+  Some(shape)
+}
+
+/// `try_reshape` with one dimension of the new shape possibly inferred (see
+/// `infer_shape`). Returns the new shape and stride.
+pub fn try_reshape_infer<I: IndexSlice>(shape: &I, stride: &I, dims: &[isize]) -> Option<(IndexNd, IndexNd)> {
+  let len = checked_len(shape)?;
+  let new_shape = infer_shape(len, dims)?;
+  let new_stride = try_reshape(shape, stride, &new_shape)?;
+  Some((new_shape, new_stride))
+}
+
+/// `checked_flat_len`, except that a shape with a size-0 axis always has
+/// length 0, even if its other axes alone would overflow.
+fn checked_len<I: IndexSlice>(shape: &I) -> Option<usize> {
+  if shape.as_slice().contains(&0) {
+    return Some(0);
+  }
+  shape.checked_flat_len()
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  /// A view of the same elements with shape `new_shape`, if one exists
+  /// without copying; see `try_reshape`.
+  pub fn try_reshape<O: IndexSlice>(&self, new_shape: &O) -> Option<ViewDesc<O>> {
+    let stride = try_reshape(&self.shape, &self.stride, new_shape)?;
+    Some(ViewDesc{shape: new_shape.clone(), stride, offset: self.offset})
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use {ArrayIndex};
+
+  #[test]
+  fn try_reshape_merges_and_splits_packed_axes() {
+    let shape: [usize; 3] = [2, 3, 4];
+    let stride = shape.to_packed_stride();
+    assert_eq!(try_reshape(&shape, &stride, &[6, 4]), Some([1, 6]));
+    assert_eq!(try_reshape(&shape, &stride, &[24]), Some([1]));
+    assert_eq!(try_reshape(&shape, &stride, &[2, 3, 2, 2]), Some([1, 2, 6, 12]));
+    assert_eq!(try_reshape(&shape, &stride, &[4, 6]), Some([1, 4]));
+    assert_eq!(try_reshape(&shape, &stride, &[5, 5]), None);
+  }
+
+  #[test]
+  fn try_reshape_keeps_strided_groups() {
+    // Every other element of a 2 x 6 buffer along axis 1.
+    let shape = [2, 3];
+    let stride = [1, 4];
+    assert_eq!(try_reshape(&shape, &stride, &[2, 3, 1]), Some([1, 4, 12]));
+    assert_eq!(try_reshape(&shape, &stride, &[1, 2, 1, 3]), Some([1, 1, 4, 4]));
+    assert_eq!(try_reshape(&shape, &stride, &[6]), None);
+    assert_eq!(try_reshape(&shape, &stride, &[3, 2]), None);
+  }
+
+  #[test]
+  fn try_reshape_ignores_size_1_axes() {
+    let shape = [3, 1, 4];
+    let stride = [1, 100, 3];
+    assert_eq!(try_reshape(&shape, &stride, &[12]), Some([1]));
+    assert_eq!(try_reshape(&shape, &stride, &[0usize; 0]), None);
+    assert_eq!(try_reshape(&[1, 1], &[7, 9], &[1]), Some([1]));
+    assert_eq!(try_reshape(&[0, 5], &[1, 0], &[5, 0]), Some([1, 5]));
+  }
+
+  #[test]
+  fn infer_shape_resolves_one_dim() {
+    assert_eq!(infer_shape(24, &[2, -1, 4]), Some(IndexNd::from(vec![2, 3, 4])));
+    assert_eq!(infer_shape(24, &[-1]), Some(IndexNd::from(vec![24])));
+    assert_eq!(infer_shape(24, &[5, -1]), None);
+    assert_eq!(infer_shape(24, &[-1, -1]), None);
+    assert_eq!(infer_shape(24, &[-2, 12]), None);
+    assert_eq!(infer_shape(0, &[0, -1]), None);
+    assert_eq!(infer_shape(0, &[isize::MAX, isize::MAX, 0]), Some(IndexNd::from(vec![isize::MAX as usize, isize::MAX as usize, 0])));
+    assert_eq!(infer_shape(24, &[isize::MAX, isize::MAX, -1]), None);
+    assert_eq!(
+      try_reshape_infer(&[2, 3, 4], &[1, 2, 6], &[-1, 4]),
+      Some((IndexNd::from(vec![6, 4]), IndexNd::from(vec![1, 6]))),
+    );
+  }
+}
@@ -0,0 +1,397 @@
+use {ArrayIdxError, ArrayIndex, HasAbove, HasBelow, Index0d, Index1d, IndexNd, IndexSlice, Layout, SIndexNd, SIndexSlice, to_signed_into, unwrap_axis};
+
+use std::convert::{TryFrom};
+use std::fmt;
+use std::ops::{Index};
+
+/// The maximum rank of a `SmallIndexNd`.
+pub const SMALL_INDEX_CAP: usize = 8;
+
+/// A dynamic-rank index stored inline, without a heap allocation, for ranks
+/// up to `SMALL_INDEX_CAP`. Supports the same API as `IndexNd`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SmallIndexNd {
+  // Components past `len` are always zero, so the derived impls only see
+  // the active components.
+  len: usize,
+  buf: [usize; SMALL_INDEX_CAP],
+}
+
+impl fmt::Debug for SmallIndexNd {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("SmallIndexNd")
+      .field("components", &self.as_slice())
+      .finish()
+  }
+}
+
+impl Index<usize> for SmallIndexNd {
+  type Output = usize;
+
+  fn index(&self, index: usize) -> &usize {
+    &self.as_slice()[index]
+  }
+}
+
+impl SmallIndexNd {
+  /// Panics if `components` has more than `SMALL_INDEX_CAP` axes.
+  pub fn from_slice(components: &[usize]) -> Self {
+    match SmallIndexNd::try_from_slice(components) {
+      Ok(idx) => idx,
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+  pub fn zero(dim: usize) -> Self {
+    assert!(dim <= SMALL_INDEX_CAP,
+        "rank {} exceeds the capacity {} of SmallIndexNd", dim, SMALL_INDEX_CAP);
+    SmallIndexNd{len: dim, buf: [0; SMALL_INDEX_CAP]}
+  }
+
+  pub fn flat_len(&self) -> usize {
+    self.as_slice().iter().product()
+  }
+
+  pub fn index_at(&self, axis: isize) -> usize {
+    self.as_slice()[unwrap_axis(axis, self.len)]
+  }
+
+  pub fn to_packed_stride(&self) -> Self {
+    self.to_packed_stride_in(Layout::ColumnMajor)
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.as_slice().iter().all(|&c| c == 0)
+  }
+
+  pub fn is_packed(&self, stride: &Self) -> bool {
+    &self.to_packed_stride() == stride
+  }
+
+  pub fn inside(&self) -> usize {
+    self.inside_in(Layout::ColumnMajor)
+  }
+
+  pub fn outside(&self) -> usize {
+    self.outside_in(Layout::ColumnMajor)
+  }
+
+  pub fn dim(&self) -> usize {
+    self.len
+  }
+
+  pub fn ndim(&self) -> usize {
+    self.dim()
+  }
+
+  /// Splits the index around `axis`, with the same axis convention as
+  /// `IndexNd::splice_at`.
+  pub fn splice_at(&self, axis: isize) -> (SmallIndexNd, SmallIndexNd, SmallIndexNd) {
+    let axis = if axis == self.len as isize {
+      self.len
+    } else {
+      unwrap_axis(axis, self.len)
+    };
+    let mut prefix_idx = SmallIndexNd::default();
+    for prefix_axis in 0 .. axis {
+      prefix_idx.push(self.buf[prefix_axis]);
+    }
+    let mut select_idx = SmallIndexNd::default();
+    if axis < self.len {
+      select_idx.push(self.buf[axis]);
+    }
+    let mut suffix_idx = SmallIndexNd::default();
+    for suffix_axis in axis + 1 .. self.len {
+      suffix_idx.push(self.buf[suffix_axis]);
+    }
+    (prefix_idx, select_idx, suffix_idx)
+  }
+
+  fn push(&mut self, c: usize) {
+    assert!(self.len < SMALL_INDEX_CAP,
+        "rank {} exceeds the capacity {} of SmallIndexNd", self.len + 1, SMALL_INDEX_CAP);
+    self.buf[self.len] = c;
+    self.len += 1;
+  }
+
+  fn insert(&mut self, axis: usize, c: usize) {
+    self.push(0);
+    self.buf[axis .. self.len].rotate_right(1);
+    self.buf[axis] = c;
+  }
+
+  fn remove(&mut self, axis: usize) {
+    assert!(axis < self.len);
+    self.buf[axis .. self.len].rotate_left(1);
+    self.len -= 1;
+    self.buf[self.len] = 0;
+  }
+}
+
+impl From<SmallIndexNd> for IndexNd {
+  fn from(idx: SmallIndexNd) -> Self {
+    IndexNd{components: idx.as_slice().to_vec()}
+  }
+}
+
+impl From<Index0d> for SmallIndexNd {
+  fn from(_idx: Index0d) -> Self {
+    SmallIndexNd::zero(0)
+  }
+}
+
+impl From<Index1d> for SmallIndexNd {
+  fn from(idx: Index1d) -> Self {
+    SmallIndexNd::from_slice(&[idx])
+  }
+}
+
+impl TryFrom<SmallIndexNd> for Index0d {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: SmallIndexNd) -> Result<Self, ArrayIdxError> {
+    <Index0d as ArrayIndex>::try_from_slice(idx.as_slice())
+  }
+}
+
+impl TryFrom<SmallIndexNd> for Index1d {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: SmallIndexNd) -> Result<Self, ArrayIdxError> {
+    <Index1d as ArrayIndex>::try_from_slice(idx.as_slice())
+  }
+}
+
+// Only ranks within the inline capacity convert infallibly, so that a
+// larger array is rejected at compile time.
+macro_rules! impl_from_array {
+  ($($n:expr),*) => {
+    $(
+      impl From<[usize; $n]> for SmallIndexNd {
+        fn from(idx: [usize; $n]) -> Self {
+          SmallIndexNd::from_slice(&idx)
+        }
+      }
+    )*
+  };
+}
+
+impl_from_array!(0, 1, 2, 3, 4, 5, 6, 7, 8);
+
+impl<const N: usize> TryFrom<SmallIndexNd> for [usize; N] {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: SmallIndexNd) -> Result<Self, ArrayIdxError> {
+    <[usize; N] as ArrayIndex>::try_from_slice(idx.as_slice())
+  }
+}
+
+impl TryFrom<IndexNd> for SmallIndexNd {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: IndexNd) -> Result<Self, ArrayIdxError> {
+    SmallIndexNd::try_from_slice(&idx.components)
+  }
+}
+
+/// The signed counterpart of `SmallIndexNd`, also stored inline.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SmallSIndexNd {
+  // As in `SmallIndexNd`, components past `len` are always zero.
+  len: usize,
+  buf: [isize; SMALL_INDEX_CAP],
+}
+
+impl fmt::Debug for SmallSIndexNd {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("SmallSIndexNd")
+      .field("components", &self.as_signed_slice())
+      .finish()
+  }
+}
+
+impl Index<usize> for SmallSIndexNd {
+  type Output = isize;
+
+  fn index(&self, index: usize) -> &isize {
+    &self.as_signed_slice()[index]
+  }
+}
+
+impl SmallSIndexNd {
+  /// Panics if `components` has more than `SMALL_INDEX_CAP` axes.
+  pub fn from_slice(components: &[isize]) -> Self {
+    match SmallSIndexNd::try_from_slice(components) {
+      Ok(idx) => idx,
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+  pub fn try_from_slice(components: &[isize]) -> Result<Self, ArrayIdxError> {
+    if components.len() > SMALL_INDEX_CAP {
+      return Err(ArrayIdxError::CapacityExceeded{capacity: SMALL_INDEX_CAP, found: components.len()});
+    }
+    let mut idx = SmallSIndexNd::zero(components.len());
+    idx.as_mut_signed_slice().copy_from_slice(components);
+    Ok(idx)
+  }
+
+  pub fn zero(dim: usize) -> Self {
+    assert!(dim <= SMALL_INDEX_CAP,
+        "rank {} exceeds the capacity {} of SmallSIndexNd", dim, SMALL_INDEX_CAP);
+    SmallSIndexNd{len: dim, buf: [0; SMALL_INDEX_CAP]}
+  }
+
+  pub fn dim(&self) -> usize {
+    self.len
+  }
+}
+
+impl SIndexSlice for SmallSIndexNd {
+  fn as_signed_slice(&self) -> &[isize] {
+    &self.buf[.. self.len]
+  }
+
+  fn as_mut_signed_slice(&mut self) -> &mut [isize] {
+    &mut self.buf[.. self.len]
+  }
+}
+
+impl From<SmallSIndexNd> for SIndexNd {
+  fn from(idx: SmallSIndexNd) -> Self {
+    SIndexNd{components: idx.as_signed_slice().to_vec()}
+  }
+}
+
+impl TryFrom<SIndexNd> for SmallSIndexNd {
+  type Error = ArrayIdxError;
+
+  fn try_from(idx: SIndexNd) -> Result<Self, ArrayIdxError> {
+    SmallSIndexNd::try_from_slice(&idx.components)
+  }
+}
+
+impl IndexSlice for SmallIndexNd {
+  type Signed = SmallSIndexNd;
+
+  fn as_slice(&self) -> &[usize] {
+    &self.buf[.. self.len]
+  }
+
+  fn as_mut_slice(&mut self) -> &mut [usize] {
+    &mut self.buf[.. self.len]
+  }
+
+  fn to_signed(&self) -> SmallSIndexNd {
+    let mut s = SmallSIndexNd::zero(self.len);
+    to_signed_into(self.as_slice(), s.as_mut_signed_slice());
+    s
+  }
+}
+
+/// As with `IndexNd`, a rank-0 index stands in for `zero()`.
+impl ArrayIndex for SmallIndexNd {
+  fn zero() -> Self {
+    SmallIndexNd::default()
+  }
+
+  fn from_nd(nd_shape: Vec<usize>) -> Self {
+    SmallIndexNd::from_slice(&nd_shape)
+  }
+
+  fn try_from_slice(components: &[usize]) -> Result<Self, ArrayIdxError> {
+    if components.len() > SMALL_INDEX_CAP {
+      return Err(ArrayIdxError::CapacityExceeded{capacity: SMALL_INDEX_CAP, found: components.len()});
+    }
+    let mut idx = SmallIndexNd::zero(components.len());
+    idx.as_mut_slice().copy_from_slice(components);
+    Ok(idx)
+  }
+
+  fn try_from_nd(nd_shape: Vec<usize>) -> Result<Self, ArrayIdxError> {
+    SmallIndexNd::try_from_slice(&nd_shape)
+  }
+
+  fn to_nd(&self) -> Vec<usize> {
+    self.as_slice().to_vec()
+  }
+
+  fn index_add(&self, shift: &Self) -> Self {
+    assert_eq!(self.dim(), shift.dim());
+    let mut idx = *self;
+    for (i, &s) in idx.as_mut_slice().iter_mut().zip(shift.as_slice()) {
+      *i += s;
+    }
+    idx
+  }
+
+  fn index_sub(&self, shift: &Self) -> Self {
+    assert_eq!(self.dim(), shift.dim());
+    let mut idx = *self;
+    for (i, &s) in idx.as_mut_slice().iter_mut().zip(shift.as_slice()) {
+      *i -= s;
+    }
+    idx
+  }
+
+  fn to_packed_stride(&self) -> Self {
+    SmallIndexNd::to_packed_stride(self)
+  }
+
+  fn is_packed(&self, stride: &Self) -> bool {
+    SmallIndexNd::is_packed(self, stride)
+  }
+
+  fn index_at(&self, axis: isize) -> usize {
+    SmallIndexNd::index_at(self, axis)
+  }
+
+  fn flat_len(&self) -> usize {
+    SmallIndexNd::flat_len(self)
+  }
+
+  fn flat_index(&self, stride: &Self) -> usize {
+    assert_eq!(self.dim(), stride.dim());
+    self.as_slice().iter().zip(stride.as_slice())
+      .map(|(&i, &s)| i * s)
+      .sum()
+  }
+
+  fn inside(&self) -> usize {
+    SmallIndexNd::inside(self)
+  }
+
+  fn outside(&self) -> usize {
+    SmallIndexNd::outside(self)
+  }
+
+  fn dim(&self) -> usize {
+    SmallIndexNd::dim(self)
+  }
+}
+
+impl HasAbove for SmallIndexNd {
+  type Above = SmallIndexNd;
+
+  fn index_prepend(&self, major: usize) -> SmallIndexNd {
+    let mut idx = *self;
+    idx.insert(0, major);
+    idx
+  }
+
+  fn index_append(&self, minor: usize) -> SmallIndexNd {
+    let mut idx = *self;
+    idx.push(minor);
+    idx
+  }
+}
+
+impl HasBelow for SmallIndexNd {
+  type Below = SmallIndexNd;
+
+  fn index_cut(&self, axis: isize) -> SmallIndexNd {
+    let mut idx = *self;
+    idx.remove(unwrap_axis(axis, self.len));
+    idx
+  }
+}
@@ -0,0 +1,159 @@
+use {IndexSlice};
+
+/// Precomputed constants for dividing by a fixed divisor with a multiply and
+/// shifts instead of a hardware division (Granlund-Montgomery). Exact over
+/// the full `usize` range.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FastDivisor {
+  divisor: u64,
+  magic: u64,
+  shift1: u32,
+  shift2: u32,
+}
+
+impl FastDivisor {
+  pub fn new(divisor: usize) -> Self {
+    assert!(divisor > 0, "division by zero");
+    let d = divisor as u64;
+    // `l` is ceil(log2(d)), so that 2^(l-1) < d <= 2^l.
+    let l = match d {
+      1 => 0,
+      _ => 64 - (d - 1).leading_zeros(),
+    };
+    let magic = (((((1_u128 << l) - d as u128) << 64) / d as u128) + 1) as u64;
+    FastDivisor{
+      divisor: d,
+      magic,
+      shift1: l.min(1),
+      shift2: l.saturating_sub(1),
+    }
+  }
+
+  pub fn divisor(&self) -> usize {
+    self.divisor as usize
+  }
+
+  pub fn div(&self, n: usize) -> usize {
+    let n = n as u64;
+    let t = ((self.magic as u128 * n as u128) >> 64) as u64;
+    ((t + ((n - t) >> self.shift1)) >> self.shift2) as usize
+  }
+
+  pub fn divmod(&self, n: usize) -> (usize, usize) {
+    let q = self.div(n);
+    (q, n - q * self.divisor as usize)
+  }
+}
+
+/// Converts flat offsets into indices of a fixed shape, like `unflat_index`,
+/// but with the per-axis divisions replaced by precomputed `FastDivisor`s.
+#[derive(Clone, Debug)]
+pub struct Unraveler<I> {
+  shape: I,
+  divs: Vec<FastDivisor>,
+  len: usize,
+}
+
+impl<I: IndexSlice> Unraveler<I> {
+  pub fn new(shape: I) -> Self {
+    let len = shape.as_slice().iter().product();
+    // The outermost axis never needs a division: once the offset is known
+    // to be in bounds, what remains of it is the outermost component.
+    let dim = shape.as_slice().len();
+    let divs = shape.as_slice().iter().take(dim.saturating_sub(1))
+      .map(|&s| FastDivisor::new(s.max(1)))
+      .collect();
+    Unraveler{shape, divs, len}
+  }
+
+  pub fn shape(&self) -> &I {
+    &self.shape
+  }
+
+  pub fn flat_len(&self) -> usize {
+    self.len
+  }
+
+  /// Panics if `offset` is not less than the flat length of the shape.
+  pub fn unravel(&self, offset: usize) -> I {
+    match self.try_unravel(offset) {
+      Some(idx) => idx,
+      None => panic!("flat offset out of bounds: {} for shape {:?}", offset, self.shape.as_slice()),
+    }
+  }
+
+  pub fn try_unravel(&self, offset: usize) -> Option<I> {
+    if offset >= self.len {
+      return None;
+    }
+    let mut idx = self.shape.clone();
+    let mut rem = offset;
+    {
+      let components = idx.as_mut_slice();
+      for (c, div) in components.iter_mut().zip(self.divs.iter()) {
+        let (q, r) = div.divmod(rem);
+        *c = r;
+        rem = q;
+      }
+      if let Some(c) = components.last_mut() {
+        *c = rem;
+      }
+    }
+    Some(idx)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use {IndexNd};
+
+  fn boundary_values() -> Vec<usize> {
+    let mut vals = vec![1, 2, 3, 5, 7, 10, 641, 6700417];
+    for k in 1 .. usize::BITS {
+      let p = 1_usize << k;
+      vals.extend_from_slice(&[p - 1, p, p + 1]);
+    }
+    vals.extend_from_slice(&[usize::MAX / 3, usize::MAX / 2, usize::MAX - 1, usize::MAX]);
+    vals
+  }
+
+  #[test]
+  fn fast_divisor_matches_div_mod() {
+    let vals = boundary_values();
+    for &d in vals.iter() {
+      let div = FastDivisor::new(d);
+      let mut ns = vec![0, d - 1, d, d.saturating_add(1), d.saturating_mul(2).saturating_sub(1)];
+      let top = (usize::MAX / d) * d;
+      ns.extend_from_slice(&[top - 1, top, usize::MAX]);
+      ns.extend_from_slice(&vals);
+      for &n in ns.iter() {
+        assert_eq!(div.div(n), n / d, "{} / {}", n, d);
+        assert_eq!(div.divmod(n), (n / d, n % d), "{} divmod {}", n, d);
+      }
+    }
+  }
+
+  #[test]
+  fn unravel_matches_unflat_index() {
+    let shapes: Vec<[usize; 3]> = vec![
+      [3, 5, 7],
+      [1, 1, 1],
+      [usize::MAX, 1, 1],
+      [2, usize::MAX / 2, 1],
+      [641, 6700417, 1],
+      [1 << (usize::BITS / 2), (1 << (usize::BITS / 2)) - 1, 1],
+    ];
+    for shape in shapes {
+      let unraveler = Unraveler::new(shape);
+      let nd_unraveler = Unraveler::new(IndexNd::from(shape));
+      let len = unraveler.flat_len();
+      for &off in [0, 1.min(len - 1), len / 2, len.saturating_sub(2), len - 1].iter() {
+        let idx = <[usize; 3]>::unflat_index(&shape, off);
+        assert_eq!(unraveler.unravel(off), idx);
+        assert_eq!(nd_unraveler.unravel(off), IndexNd::from(idx));
+      }
+      assert_eq!(unraveler.try_unravel(len), None);
+    }
+  }
+}
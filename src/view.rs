@@ -0,0 +1,353 @@
+use {ArrayIdxError, IndexSlice, IndexNd, Index1d, Index2d, Index3d, Index4d, Index5d, Layout};
+use {SIndexSlice, SIndex1d, SIndex2d, SIndex3d, SIndex4d, SIndex5d};
+use {range2idxs_1d, range2idxs_2d, range2idxs_3d, range2idxs_4d, range2idxs_5d};
+use {range2idxs_step_1d};
+
+use std::convert::{TryFrom};
+use std::ops::{RangeBounds};
+
+/// A strided view into a flat buffer: the element at index `idx` lives at
+/// `offset + idx . stride`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ViewDesc<I> {
+  pub shape: I,
+  pub stride: I,
+  pub offset: usize,
+}
+
+impl<I: IndexSlice> ViewDesc<I> {
+  pub fn new(shape: I, stride: I, offset: usize) -> Self {
+    assert_eq!(shape.as_slice().len(), stride.as_slice().len());
+    ViewDesc{shape, stride, offset}
+  }
+
+  /// A packed, column-major view of `shape` starting at offset 0.
+  pub fn packed(shape: I) -> Self {
+    let stride = shape.to_packed_stride_in(Layout::ColumnMajor);
+    ViewDesc{shape, stride, offset: 0}
+  }
+
+  pub fn dim(&self) -> usize {
+    self.shape.as_slice().len()
+  }
+
+  pub fn flat_len(&self) -> usize {
+    self.shape.as_slice().iter().product()
+  }
+
+  pub fn flat_index(&self, idx: &I) -> usize {
+    assert_eq!(idx.as_slice().len(), self.stride.as_slice().len());
+    let disp: usize = idx.as_slice().iter().zip(self.stride.as_slice())
+      .map(|(&i, &s)| i * s)
+      .sum();
+    self.offset + disp
+  }
+
+  /// Whether the stride is the packed column-major stride of the shape. The
+  /// base offset does not matter.
+  pub fn is_packed(&self) -> bool {
+    self.shape.is_packed_in(&self.stride, Layout::ColumnMajor)
+  }
+
+  /// The minimum length of a buffer that contains every element of the view,
+  /// i.e. one past the largest reachable offset (0 for an empty view).
+  pub fn required_len(&self) -> usize {
+    self.shape.required_len(&self.stride, self.offset)
+  }
+
+  pub fn min_offset(&self) -> Option<usize> {
+    self.shape.min_offset(&self.stride, self.offset)
+  }
+
+  pub fn max_offset(&self) -> Option<usize> {
+    self.shape.max_offset(&self.stride, self.offset)
+  }
+
+  /// Whether every element of the view lies inside a buffer of length
+  /// `buf_len`.
+  pub fn fits_in(&self, buf_len: usize) -> bool {
+    self.shape.fits_in(&self.stride, self.offset, buf_len)
+  }
+
+  /// The sub-view covering `start .. end` along every axis. The stride is
+  /// unchanged; the base offset moves to the element at `start`.
+  pub fn slice_idxs(&self, start: &I, end: &I) -> Self {
+    let mut shape = self.shape.clone();
+    for (d, ((n, &s), &e)) in shape.as_mut_slice().iter_mut()
+      .zip(start.as_slice()).zip(end.as_slice()).enumerate()
+    {
+      assert!(s <= e,
+          "array bounds violation: {} greater than {} (axis {})", s, e, d);
+      assert!(e <= *n,
+          "array end bounds violation: {} greater than {} (axis {})", e, *n, d);
+      *n = e - s;
+    }
+    let offset = self.flat_index(start);
+    ViewDesc{shape, stride: self.stride.clone(), offset}
+  }
+}
+
+impl ViewDesc<Index1d> {
+  pub fn slice<R0>(&self, r0: R0) -> Self
+  where R0: RangeBounds<usize>,
+  {
+    let (start, end) = range2idxs_1d(r0, self.shape);
+    self.slice_idxs(&start, &end)
+  }
+}
+
+impl ViewDesc<Index2d> {
+  pub fn slice<R0, R1>(&self, r0: R0, r1: R1) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+  {
+    let (start, end) = range2idxs_2d(r0, r1, self.shape);
+    self.slice_idxs(&start, &end)
+  }
+}
+
+impl ViewDesc<Index3d> {
+  pub fn slice<R0, R1, R2>(&self, r0: R0, r1: R1, r2: R2) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+        R2: RangeBounds<usize>,
+  {
+    let (start, end) = range2idxs_3d(r0, r1, r2, self.shape);
+    self.slice_idxs(&start, &end)
+  }
+}
+
+impl ViewDesc<Index4d> {
+  pub fn slice<R0, R1, R2, R3>(&self, r0: R0, r1: R1, r2: R2, r3: R3) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+        R2: RangeBounds<usize>,
+        R3: RangeBounds<usize>,
+  {
+    let (start, end) = range2idxs_4d(r0, r1, r2, r3, self.shape);
+    self.slice_idxs(&start, &end)
+  }
+}
+
+impl ViewDesc<Index5d> {
+  pub fn slice<R0, R1, R2, R3, R4>(&self, r0: R0, r1: R1, r2: R2, r3: R3, r4: R4) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+        R2: RangeBounds<usize>,
+        R3: RangeBounds<usize>,
+        R4: RangeBounds<usize>,
+  {
+    let (start, end) = range2idxs_5d(r0, r1, r2, r3, r4, self.shape);
+    self.slice_idxs(&start, &end)
+  }
+}
+
+impl ViewDesc<IndexNd> {
+  /// Slice with one range per axis. To mix kinds of ranges, pass them as
+  /// `(Bound<usize>, Bound<usize>)` pairs.
+  pub fn slice<R>(&self, ranges: &[R]) -> Self
+  where R: RangeBounds<usize>,
+  {
+    assert_eq!(self.dim(), ranges.len());
+    let mut start = IndexNd::zero(self.dim());
+    let mut end = IndexNd::zero(self.dim());
+    for (d, r) in ranges.iter().enumerate() {
+      let (s, e) = range2idxs_1d((r.start_bound().cloned(), r.end_bound().cloned()), self.shape[d]);
+      start.components[d] = s;
+      end.components[d] = e;
+    }
+    self.slice_idxs(&start, &end)
+  }
+}
+
+/// A strided view whose stride may be negative, e.g. after reversing an axis.
+/// The base offset is that of the element at the zero index, which need not
+/// be the lowest offset in the view.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SViewDesc<I: IndexSlice> {
+  pub shape: I,
+  pub stride: I::Signed,
+  pub offset: usize,
+}
+
+/// Fails with `ArrayIdxError::Overflow` if a stride or the base offset
+/// exceeds `isize::MAX`.
+impl<I: IndexSlice> TryFrom<ViewDesc<I>> for SViewDesc<I> {
+  type Error = ArrayIdxError;
+
+  fn try_from(view: ViewDesc<I>) -> Result<Self, ArrayIdxError> {
+    if isize::try_from(view.offset).is_err() {
+      return Err(ArrayIdxError::Overflow);
+    }
+    let stride = view.stride.try_to_signed()?;
+    Ok(SViewDesc{shape: view.shape, stride, offset: view.offset})
+  }
+}
+
+impl<I: IndexSlice> SViewDesc<I> {
+  pub fn new(shape: I, stride: I::Signed, offset: usize) -> Self {
+    assert_eq!(shape.as_slice().len(), stride.as_signed_slice().len());
+    SViewDesc{shape, stride, offset}
+  }
+
+  pub fn dim(&self) -> usize {
+    self.shape.as_slice().len()
+  }
+
+  pub fn flat_len(&self) -> usize {
+    self.shape.as_slice().iter().product()
+  }
+
+  pub fn flat_index(&self, idx: &I) -> usize {
+    (self.offset as isize + idx.flat_index_signed(&self.stride)) as usize
+  }
+
+  pub fn min_offset(&self) -> Option<isize> {
+    self.shape.min_offset_signed(&self.stride, self.offset)
+  }
+
+  pub fn max_offset(&self) -> Option<isize> {
+    self.shape.max_offset_signed(&self.stride, self.offset)
+  }
+
+  /// The minimum length of a buffer that contains every element of the view,
+  /// or `None` if some element would lie before the start of the buffer.
+  pub fn required_len(&self) -> Option<usize> {
+    match (self.min_offset(), self.max_offset()) {
+      (Some(lo), _) if lo < 0 => None,
+      (_, Some(hi)) => Some(hi as usize + 1),
+      _ => Some(0),
+    }
+  }
+
+  /// Whether every element of the view lies inside a buffer of length
+  /// `buf_len`.
+  pub fn fits_in(&self, buf_len: usize) -> bool {
+    self.shape.fits_in_signed(&self.stride, self.offset, buf_len)
+  }
+
+  /// Reverse the direction of one axis: index `i` of the result is index
+  /// `n - 1 - i` of `self`.
+  pub fn flip_axis(&self, axis: usize) -> Self {
+    let n = self.shape.as_slice()[axis];
+    let s = self.stride.as_signed_slice()[axis];
+    let mut view = self.clone();
+    view.stride.as_mut_signed_slice()[axis] = -s;
+    if n > 0 {
+      view.offset = (self.offset as isize + (n - 1) as isize * s) as usize;
+    }
+    view
+  }
+
+  /// Reverse the direction of every axis, so that iterating the result in
+  /// order visits the elements of `self` in reverse.
+  pub fn flip(&self) -> Self {
+    let mut view = self.clone();
+    for axis in 0 .. self.dim() {
+      view = view.flip_axis(axis);
+    }
+    view
+  }
+
+  /// Convert back to an unsigned view, or `None` if any stride is negative.
+  pub fn to_unsigned(&self) -> Option<ViewDesc<I>> {
+    if !self.stride.is_nonnegative() {
+      return None;
+    }
+    let mut stride = self.shape.clone();
+    for (u, &s) in stride.as_mut_slice().iter_mut().zip(self.stride.as_signed_slice()) {
+      *u = s as usize;
+    }
+    Some(ViewDesc{shape: self.shape.clone(), stride, offset: self.offset})
+  }
+
+  /// NumPy-style `start:stop:step` slicing of a single axis; see
+  /// `range2idxs_step_1d` for how the range and step are normalized.
+  pub fn slice_step_axis<R>(&self, axis: usize, r: R, step: isize) -> Self
+  where R: RangeBounds<usize>,
+  {
+    let (first, len) = range2idxs_step_1d(r, step, self.shape.as_slice()[axis]);
+    let s = self.stride.as_signed_slice()[axis];
+    let mut view = self.clone();
+    view.shape.as_mut_slice()[axis] = len;
+    view.stride.as_mut_signed_slice()[axis] = s * step;
+    view.offset = (self.offset as isize + first as isize * s) as usize;
+    view
+  }
+}
+
+impl SViewDesc<Index1d> {
+  pub fn slice_step<R0>(&self, r0: R0, step: SIndex1d) -> Self
+  where R0: RangeBounds<usize>,
+  {
+    self.slice_step_axis(0, r0, step)
+  }
+}
+
+impl SViewDesc<Index2d> {
+  pub fn slice_step<R0, R1>(&self, r0: R0, r1: R1, step: SIndex2d) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+  {
+    self.slice_step_axis(0, r0, step[0])
+        .slice_step_axis(1, r1, step[1])
+  }
+}
+
+impl SViewDesc<Index3d> {
+  pub fn slice_step<R0, R1, R2>(&self, r0: R0, r1: R1, r2: R2, step: SIndex3d) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+        R2: RangeBounds<usize>,
+  {
+    self.slice_step_axis(0, r0, step[0])
+        .slice_step_axis(1, r1, step[1])
+        .slice_step_axis(2, r2, step[2])
+  }
+}
+
+impl SViewDesc<Index4d> {
+  pub fn slice_step<R0, R1, R2, R3>(&self, r0: R0, r1: R1, r2: R2, r3: R3, step: SIndex4d) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+        R2: RangeBounds<usize>,
+        R3: RangeBounds<usize>,
+  {
+    self.slice_step_axis(0, r0, step[0])
+        .slice_step_axis(1, r1, step[1])
+        .slice_step_axis(2, r2, step[2])
+        .slice_step_axis(3, r3, step[3])
+  }
+}
+
+impl SViewDesc<Index5d> {
+  pub fn slice_step<R0, R1, R2, R3, R4>(&self, r0: R0, r1: R1, r2: R2, r3: R3, r4: R4, step: SIndex5d) -> Self
+  where R0: RangeBounds<usize>,
+        R1: RangeBounds<usize>,
+        R2: RangeBounds<usize>,
+        R3: RangeBounds<usize>,
+        R4: RangeBounds<usize>,
+  {
+    self.slice_step_axis(0, r0, step[0])
+        .slice_step_axis(1, r1, step[1])
+        .slice_step_axis(2, r2, step[2])
+        .slice_step_axis(3, r3, step[3])
+        .slice_step_axis(4, r4, step[4])
+  }
+}
+
+impl SViewDesc<IndexNd> {
+  /// Slice with one range and one step per axis. To mix kinds of ranges,
+  /// pass them as `(Bound<usize>, Bound<usize>)` pairs.
+  pub fn slice_step<R>(&self, ranges: &[R], step: &[isize]) -> Self
+  where R: RangeBounds<usize>,
+  {
+    assert_eq!(self.dim(), ranges.len());
+    assert_eq!(self.dim(), step.len());
+    let mut view = self.clone();
+    for (d, (r, &st)) in ranges.iter().zip(step).enumerate() {
+      view = view.slice_step_axis(d, (r.start_bound().cloned(), r.end_bound().cloned()), st);
+    }
+    view
+  }
+}